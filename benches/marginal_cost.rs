@@ -0,0 +1,36 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use grapevine_sonobe::{
+    nova::GrapevineProver,
+    params::test_nova_setup,
+    test_utils::{degree_input, sample_circuit, MAX_SAMPLE_DEGREE},
+    utils::inputs::get_z0,
+};
+
+/**
+ * Times folding degree N on top of an already folded chain of degree N - 1
+ * @dev nova's running instance does not grow with the chain, so every degree should cost about
+ *      the same. `test_utils::measure_marginal_cost` gives a single pass of the same numbers
+ */
+fn bench_marginal_cost(c: &mut Criterion) {
+    let f_circuit = sample_circuit();
+    let (prover_params, _) = test_nova_setup(f_circuit.clone());
+    let mut prover =
+        GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+
+    let mut group = c.benchmark_group("marginal_cost");
+    group.sample_size(10);
+    for degree in 1..=MAX_SAMPLE_DEGREE {
+        group.bench_function(format!("degree {}", degree), |b| {
+            b.iter_batched(
+                || (prover.clone(), degree_input(degree)),
+                |(mut prover, input)| prover.add_degree(input).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+        prover.add_degree(degree_input(degree)).unwrap();
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_marginal_cost);
+criterion_main!(benches);
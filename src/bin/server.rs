@@ -0,0 +1,274 @@
+use ark_pallas::Fr;
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use grapevine_sonobe::{
+    circom::GrapevineFCircuit,
+    errors::GrapevineError,
+    nova::prove_chain,
+    params::{
+        check_params_match_artifacts, FingerprintedProverParams, GrapevineProverParams,
+        GrapevineVerifierParams, ProverParamsBuilder,
+    },
+    proof::{verify_and_extract, GrapevineProof},
+    utils::{
+        inputs::{inputs_from_json, validate_input_sequence},
+        wrapper::CircomWrapper,
+    },
+};
+use serde::{Deserialize, Serialize};
+use sonobe::frontend::FCircuit;
+use std::{fs::File, io::BufReader, path::PathBuf, sync::Arc};
+
+// Circuit and params shared by every request
+struct AppState {
+    f_circuit: GrapevineFCircuit<Fr>,
+    prover_params: GrapevineProverParams,
+    verifier_params: GrapevineVerifierParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProofBody {
+    // hex encoded serialized proof
+    proof: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VerifyResponse {
+    valid: bool,
+    degree: Option<u64>,
+    error: Option<String>,
+}
+
+type ApiError = (StatusCode, String);
+
+// maps a grapevine error to a response with its status
+fn error_response(e: GrapevineError) -> ApiError {
+    let status =
+        StatusCode::from_u16(e.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, e.to_string())
+}
+
+/**
+ * Folds a chain submitted as a JSON array of step inputs
+ * @dev folding is CPU bound so it runs on the blocking thread pool
+ *
+ * @param state - the shared circuit and params
+ * @param doc - the JSON chain document
+ * @return - the hex encoded serialized proof
+ */
+async fn prove(
+    State(state): State<Arc<AppState>>,
+    Json(doc): Json<serde_json::Value>,
+) -> Result<Json<ProofBody>, ApiError> {
+    let inputs = inputs_from_json(&doc).map_err(error_response)?;
+    validate_input_sequence(&inputs).map_err(error_response)?;
+    let proof = tokio::task::spawn_blocking(move || {
+        prove_chain(&state.prover_params, state.f_circuit.clone(), &inputs)?.to_bytes()
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(error_response)?;
+    Ok(Json(ProofBody {
+        proof: hex::encode(proof),
+    }))
+}
+
+/**
+ * Verifies a submitted proof
+ *
+ * @param state - the shared circuit and params
+ * @param body - the hex encoded serialized proof
+ * @return - whether the proof is valid and the degree it proves
+ */
+async fn verify_proof(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<ProofBody>,
+) -> Result<Json<VerifyResponse>, ApiError> {
+    let bytes = hex::decode(&body.proof).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let proof = GrapevineProof::from_bytes(&bytes).map_err(error_response)?;
+    let result = tokio::task::spawn_blocking(move || {
+        verify_and_extract(&state.verifier_params, &proof).map(|claim| claim.degree)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(match result {
+        Ok(degree) => VerifyResponse {
+            valid: true,
+            degree: Some(degree),
+            error: None,
+        },
+        Err(e) => VerifyResponse {
+            valid: false,
+            degree: None,
+            error: Some(e.to_string()),
+        },
+    }))
+}
+
+// builds the router exposing the prove and verify endpoints
+fn app(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/prove", post(prove))
+        .route("/verify", post(verify_proof))
+        .with_state(state)
+}
+
+/**
+ * Loads the params for the circuit at the given artifacts
+ * @dev the params must be generated ahead of time with `nova_setup` over an os rng and saved
+ *      with `FingerprintedProverParams::save`. Params derived from a public seed, as
+ *      `test_nova_setup` does, let anyone forge proofs
+ *
+ * @param r1cs_path - the circuit's r1cs
+ * @param wasm_path - the circuit's witness calculator
+ * @param params_path - the saved fingerprinted prover params
+ * @return - the shared state, or an error if the params are unreadable or stale
+ */
+fn setup(
+    r1cs_path: PathBuf,
+    wasm_path: PathBuf,
+    params_path: PathBuf,
+) -> Result<AppState, GrapevineError> {
+    let file = File::open(&params_path).map_err(|e| {
+        GrapevineError::ArtifactError(format!("{}: {}", params_path.display(), e))
+    })?;
+    let params = FingerprintedProverParams::load(BufReader::new(file))?;
+    check_params_match_artifacts(
+        &params,
+        &CircomWrapper::<Fr>::new(r1cs_path.clone(), wasm_path.clone()),
+    )?;
+
+    // the verifier params are derived from the circuit the pedersen params were checked against
+    let f_circuit = GrapevineFCircuit::<Fr>::new((r1cs_path, wasm_path));
+    let (prover_params, verifier_params) = ProverParamsBuilder::new(f_circuit.clone())
+        .cs_params(params.prover_params.cs_params)
+        .cf_cs_params(params.prover_params.cf_cs_params)
+        .build()?;
+    Ok(AppState {
+        f_circuit,
+        prover_params,
+        verifier_params,
+    })
+}
+
+#[tokio::main]
+async fn main() {
+    let env_path = |key: &str, default: &str| {
+        PathBuf::from(std::env::var(key).unwrap_or_else(|_| default.to_string()))
+    };
+    let r1cs_path = env_path("GRAPEVINE_R1CS", "./circom/artifacts/grapevine.r1cs");
+    let wasm_path = env_path("GRAPEVINE_WASM", "./circom/artifacts/grapevine.wasm");
+    let params_path = env_path("GRAPEVINE_PARAMS", "./params.bin");
+    let addr = std::env::var("GRAPEVINE_ADDR").unwrap_or_else(|_| "127.0.0.1:3000".to_string());
+
+    let state = match setup(r1cs_path, wasm_path, params_path) {
+        Ok(state) => Arc::new(state),
+        Err(e) => panic!("Failed to load params: {}", e),
+    };
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    println!("Listening on {}", addr);
+    axum::serve(listener, app(state)).await.unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use grapevine_sonobe::{
+        nova::GrapevineProver,
+        params::test_nova_setup,
+        utils::inputs::{inputs_to_json, reconstruct_inputs},
+    };
+    use http_body_util::BodyExt;
+    use num_bigint::BigInt;
+    use tower::ServiceExt;
+
+    const R1CS_PATH: &str = "./circom/artifacts/grapevine.r1cs";
+    const WASM_PATH: &str = "./circom/artifacts/grapevine.wasm";
+
+    // saves params for the bundled artifacts and loads the server state from them
+    fn test_state(name: &str) -> AppState {
+        let (r1cs_path, wasm_path) = (PathBuf::from(R1CS_PATH), PathBuf::from(WASM_PATH));
+        let f_circuit = GrapevineFCircuit::<Fr>::new((r1cs_path.clone(), wasm_path.clone()));
+        let (prover_params, _) = test_nova_setup(f_circuit);
+        let wrapper = CircomWrapper::<Fr>::new(r1cs_path.clone(), wasm_path.clone());
+        let params_path = std::env::temp_dir().join(name);
+        FingerprintedProverParams::new(prover_params, &wrapper)
+            .unwrap()
+            .save(File::create(&params_path).unwrap())
+            .unwrap();
+        setup(r1cs_path, wasm_path, params_path).unwrap()
+    }
+
+    // posts a json body to the app and returns the status and parsed response
+    async fn post_json(
+        app: Router,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_prove_and_verify() {
+        let app = app(Arc::new(test_state("grapevine_server_prove_and_verify.bin")));
+
+        // prove a degree 2 chain
+        let path = vec![
+            (String::from("alice"), BigInt::from(1)),
+            (String::from("bob"), BigInt::from(2)),
+        ];
+        let inputs = reconstruct_inputs(&String::from("This is a secret"), &path).unwrap();
+        let (status, body) = post_json(app.clone(), "/prove", inputs_to_json(&inputs)).await;
+        assert_eq!(status, StatusCode::OK);
+        let proof = body["proof"].clone();
+
+        // verify the returned proof
+        let (status, body) =
+            post_json(app.clone(), "/verify", serde_json::json!({ "proof": proof })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["valid"], serde_json::json!(true));
+
+        // a malformed chain is rejected before folding
+        let mut malformed = inputs_to_json(&inputs);
+        malformed.as_array_mut().unwrap().remove(1);
+        let (status, _) = post_json(app, "/prove", malformed).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_forged_start() {
+        let state = test_state("grapevine_server_forged_start.bin");
+
+        // a proof folded from a non-zero state verifies but proves no degree
+        let z_0 = vec![Fr::from(100), Fr::from(1), Fr::from(2), Fr::from(0)];
+        let mut prover =
+            GrapevineProver::new(&state.prover_params, state.f_circuit.clone(), z_0).unwrap();
+        let path = vec![(String::from("alice"), BigInt::from(1))];
+        let inputs = reconstruct_inputs(&String::from("This is a secret"), &path).unwrap();
+        prover.add_degree(inputs[0].clone()).unwrap();
+        let proof = hex::encode(prover.finalize().unwrap().to_bytes().unwrap());
+
+        let (status, body) =
+            post_json(app(Arc::new(state)), "/verify", serde_json::json!({ "proof": proof })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["valid"], serde_json::json!(false));
+        assert_eq!(body["degree"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_setup_requires_params() {
+        let missing = setup(
+            PathBuf::from(R1CS_PATH),
+            PathBuf::from(WASM_PATH),
+            std::env::temp_dir().join("grapevine_server_missing_params.bin"),
+        );
+        assert!(matches!(missing, Err(GrapevineError::ArtifactError(_))));
+    }
+}
@@ -1,16 +1,269 @@
 use serde::{Deserialize, Serialize};
 
+// Whether a fold step proves a degree or is chaff hiding the previous one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepKind {
+    Logic,
+    Chaff,
+}
+
+impl std::fmt::Display for StepKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StepKind::Logic => write!(f, "logic"),
+            StepKind::Chaff => write!(f, "chaff"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum GrapevineError {
     InputsEmpty,
+    FoldingError(String),
+    NoCheckpoint,
+    WitnessLengthMismatch { expected: usize, got: usize },
+    VerificationFailed(String),
+    FieldConversion(String),
+    NonCanonicalState { index: usize },
+    HashError(String),
+    InvalidInput(String),
+    SerializationError(String),
+    StateMismatch { step: usize },
+    WitnessTooLarge { size: usize, max: usize },
+    InconsistentChaff,
+    WitnessUnsatisfied { constraint: String },
+    SynthesisFailed(String),
+    PhraseHashMismatch,
+    ArtifactError(String),
+    InvalidSequence { index: usize, reason: String },
+    ParamsMismatch,
+    ChaffMutatedState { index: usize },
+    ChainDiscontinuity(String),
+    ParamsLengthMismatch { expected: usize, got: usize },
+    OddStepCount(usize),
+    InvalidUsernameChar { ch: char },
+    StepKindMismatch { step: usize, expected: StepKind, got: StepKind },
+    ChaffNotUniform { bucket: usize, count: usize, expected: usize },
+    DegreeTooLow,
+    UnsupportedVersion { found: u8, supported: (u8, u8) },
+    StaleParams { params: String, artifacts: String },
+    ReservedSlotNonZero { index: usize },
+    NoStepsFolded,
+    CircuitVersionMixed { folding: String, got: String },
+    PoseidonMismatch,
+    NotFreshStart,
 }
 
 impl std::fmt::Display for GrapevineError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            GrapevineError::InputsEmpty => write!(f, "No private input provided to F circuit!")
+            GrapevineError::InputsEmpty => write!(f, "No private input provided to F circuit!"),
+            GrapevineError::FoldingError(msg) => write!(f, "Folding error: {}", msg),
+            GrapevineError::NoCheckpoint => write!(f, "No checkpoint to roll back to"),
+            GrapevineError::WitnessLengthMismatch { expected, got } => write!(
+                f,
+                "Witness length mismatch: expected at least {}, got {}",
+                expected, got
+            ),
+            GrapevineError::VerificationFailed(msg) => write!(f, "Verification failed: {}", msg),
+            GrapevineError::FieldConversion(msg) => write!(f, "Field conversion error: {}", msg),
+            GrapevineError::NonCanonicalState { index } => {
+                write!(f, "State element {} is not a canonical field element", index)
+            }
+            GrapevineError::HashError(msg) => write!(f, "Hash error: {}", msg),
+            GrapevineError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            GrapevineError::SerializationError(msg) => write!(f, "Serialization error: {}", msg),
+            GrapevineError::StateMismatch { step } => {
+                write!(f, "Native and constrained states diverge at step {}", step)
+            }
+            GrapevineError::WitnessTooLarge { size, max } => {
+                write!(f, "Witness of {} elements exceeds maximum of {}", size, max)
+            }
+            GrapevineError::InconsistentChaff => {
+                write!(f, "Chaff inputs cannot carry a phrase, usernames or auth secrets")
+            }
+            GrapevineError::WitnessUnsatisfied { constraint } => {
+                write!(f, "Witness does not satisfy constraint {}", constraint)
+            }
+            GrapevineError::SynthesisFailed(msg) => write!(f, "Circuit synthesis failed: {}", msg),
+            GrapevineError::PhraseHashMismatch => {
+                write!(f, "Proof does not carry the claimed phrase hash")
+            }
+            GrapevineError::ArtifactError(msg) => write!(f, "Circom artifact error: {}", msg),
+            GrapevineError::InvalidSequence { index, reason } => {
+                write!(f, "Invalid input sequence at step {}: {}", index, reason)
+            }
+            GrapevineError::ParamsMismatch => {
+                write!(f, "Proof was generated against different params")
+            }
+            GrapevineError::ChaffMutatedState { index } => {
+                write!(f, "Chaff step mutated state at index {}", index)
+            }
+            GrapevineError::ChainDiscontinuity(msg) => write!(f, "Chain discontinuity: {}", msg),
+            GrapevineError::ParamsLengthMismatch { expected, got } => {
+                write!(f, "Expected params for {} generators, got {}", expected, got)
+            }
+            GrapevineError::OddStepCount(steps) => {
+                write!(f, "Proof folds an odd number of steps ({})", steps)
+            }
+            GrapevineError::InvalidUsernameChar { ch } => {
+                write!(f, "Username contains disallowed character {:?}", ch)
+            }
+            GrapevineError::StepKindMismatch {
+                step,
+                expected,
+                got,
+            } => write!(f, "Step {} expects a {} input, got {}", step, expected, got),
+            GrapevineError::ChaffNotUniform {
+                bucket,
+                count,
+                expected,
+            } => write!(
+                f,
+                "Chaff bucket {} holds {} samples, expected about {}",
+                bucket, count, expected
+            ),
+            GrapevineError::DegreeTooLow => {
+                write!(f, "A chain must prove at least degree 1 (the phrase holder)")
+            }
+            GrapevineError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Proof format version {} is not supported (supported {}..={})",
+                found, supported.0, supported.1
+            ),
+            GrapevineError::StaleParams { params, artifacts } => write!(
+                f,
+                "Params were generated for circuit {} but the artifacts are circuit {}",
+                params, artifacts
+            ),
+            GrapevineError::ReservedSlotNonZero { index } => {
+                write!(f, "Reserved state slot {} is not zero", index)
+            }
+            GrapevineError::NoStepsFolded => {
+                write!(f, "No steps were folded, there is nothing to prove")
+            }
+            GrapevineError::CircuitVersionMixed { folding, got } => write!(
+                f,
+                "Cannot fold circuit {} into a fold over circuit {}",
+                got, folding
+            ),
+            GrapevineError::PoseidonMismatch => {
+                write!(f, "Poseidon config does not match the one the circuit was compiled with")
+            }
+            GrapevineError::NotFreshStart => {
+                write!(f, "Proof does not start from the fresh all-zero state")
+            }
+        }
+    }
+}
+
+impl GrapevineError {
+    /**
+     * Maps the error to the HTTP status a service should respond with
+     * @dev 400 for malformed or invalid input, 422 for proofs that do not verify and 500 for
+     *      artifact, configuration or internal failures
+     *
+     * @return - the HTTP status code
+     */
+    pub fn http_status(&self) -> u16 {
+        match self {
+            GrapevineError::InputsEmpty
+            | GrapevineError::NoCheckpoint
+            | GrapevineError::FieldConversion(_)
+            | GrapevineError::NonCanonicalState { .. }
+            | GrapevineError::InvalidInput(_)
+            | GrapevineError::SerializationError(_)
+            | GrapevineError::WitnessTooLarge { .. }
+            | GrapevineError::InconsistentChaff
+            | GrapevineError::InvalidSequence { .. }
+            | GrapevineError::InvalidUsernameChar { .. }
+            | GrapevineError::StepKindMismatch { .. }
+            | GrapevineError::DegreeTooLow
+            | GrapevineError::UnsupportedVersion { .. }
+            | GrapevineError::NoStepsFolded => 400,
+            GrapevineError::VerificationFailed(_)
+            | GrapevineError::WitnessUnsatisfied { .. }
+            | GrapevineError::PhraseHashMismatch
+            | GrapevineError::ParamsMismatch
+            | GrapevineError::ChainDiscontinuity(_)
+            | GrapevineError::OddStepCount(_)
+            | GrapevineError::NotFreshStart => 422,
+            GrapevineError::FoldingError(_)
+            | GrapevineError::WitnessLengthMismatch { .. }
+            | GrapevineError::HashError(_)
+            | GrapevineError::StateMismatch { .. }
+            | GrapevineError::SynthesisFailed(_)
+            | GrapevineError::ArtifactError(_)
+            | GrapevineError::ChaffMutatedState { .. }
+            | GrapevineError::ParamsLengthMismatch { .. }
+            | GrapevineError::ChaffNotUniform { .. }
+            | GrapevineError::StaleParams { .. }
+            | GrapevineError::ReservedSlotNonZero { .. }
+            | GrapevineError::CircuitVersionMixed { .. }
+            | GrapevineError::PoseidonMismatch => 500,
         }
     }
 }
 
 impl std::error::Error for GrapevineError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_http_status() {
+        let cases = [
+            (GrapevineError::InputsEmpty, 400),
+            (GrapevineError::NoCheckpoint, 400),
+            (GrapevineError::FieldConversion(String::new()), 400),
+            (GrapevineError::NonCanonicalState { index: 0 }, 400),
+            (GrapevineError::InvalidInput(String::new()), 400),
+            (GrapevineError::SerializationError(String::new()), 400),
+            (GrapevineError::WitnessTooLarge { size: 2, max: 1 }, 400),
+            (GrapevineError::InconsistentChaff, 400),
+            (GrapevineError::InvalidSequence { index: 0, reason: String::new() }, 400),
+            (GrapevineError::InvalidUsernameChar { ch: ' ' }, 400),
+            (GrapevineError::DegreeTooLow, 400),
+            (GrapevineError::UnsupportedVersion { found: 2, supported: (1, 1) }, 400),
+            (GrapevineError::NoStepsFolded, 400),
+            (
+                GrapevineError::StepKindMismatch {
+                    step: 0,
+                    expected: StepKind::Logic,
+                    got: StepKind::Chaff,
+                },
+                400,
+            ),
+            (GrapevineError::VerificationFailed(String::new()), 422),
+            (GrapevineError::WitnessUnsatisfied { constraint: String::new() }, 422),
+            (GrapevineError::PhraseHashMismatch, 422),
+            (GrapevineError::ParamsMismatch, 422),
+            (GrapevineError::ChainDiscontinuity(String::new()), 422),
+            (GrapevineError::OddStepCount(1), 422),
+            (GrapevineError::FoldingError(String::new()), 500),
+            (GrapevineError::WitnessLengthMismatch { expected: 2, got: 1 }, 500),
+            (GrapevineError::HashError(String::new()), 500),
+            (GrapevineError::StateMismatch { step: 0 }, 500),
+            (GrapevineError::SynthesisFailed(String::new()), 500),
+            (GrapevineError::ArtifactError(String::new()), 500),
+            (GrapevineError::ChaffMutatedState { index: 0 }, 500),
+            (GrapevineError::ParamsLengthMismatch { expected: 2, got: 1 }, 500),
+            (GrapevineError::ChaffNotUniform { bucket: 0, count: 0, expected: 1 }, 500),
+            (GrapevineError::StaleParams { params: String::new(), artifacts: String::new() }, 500),
+            (GrapevineError::ReservedSlotNonZero { index: 3 }, 500),
+            (
+                GrapevineError::CircuitVersionMixed {
+                    folding: String::new(),
+                    got: String::new(),
+                },
+                500,
+            ),
+            (GrapevineError::PoseidonMismatch, 500),
+            (GrapevineError::NotFreshStart, 422),
+        ];
+        for (error, status) in cases {
+            assert_eq!(error.http_status(), status, "{:?}", error);
+        }
+    }
+}
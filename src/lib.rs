@@ -1,5 +1,9 @@
 pub mod circom;
 pub mod nova;
 pub mod params;
+pub mod proof;
 pub mod utils;
+pub mod vectors;
 pub mod errors;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
@@ -1,40 +1,1135 @@
-use ark_crypto_primitives::{
-    crh::{
-        poseidon::constraints::{CRHGadget, CRHParametersVar},
-        poseidon::CRH,
-        CRHScheme, CRHSchemeGadget,
-    },
-    sponge::{poseidon::PoseidonConfig, Absorb},
-};
-use ark_ff::PrimeField;
+use ark_ec::CurveGroup;
+use ark_ff::Zero;
 use ark_pallas::{constraints::GVar, Fr, Projective};
-use ark_r1cs_std::{alloc::AllocVar, fields::FieldVar, fields::fp::FpVar};
-use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
-use ark_std::Zero;
-use ark_vesta::{constraints::GVar as Gvar2, Projective as Projective2};
-use core::marker::PhantomData;
-use std::time::Instant;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_vesta::{constraints::GVar as GVar2, Projective as Projective2};
+use num_bigint::BigInt;
+use serde::Serialize;
 use sonobe::{
-    commitment::{pedersen::Pedersen, CommitmentScheme},
-    folding::nova::{get_r1cs, ProverParams, VerifierParams},
+    commitment::pedersen::Pedersen,
+    folding::nova::{CommittedInstance, Nova, Witness},
     frontend::FCircuit,
-    transcript::poseidon::poseidon_test_config
+    FoldingScheme,
+};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    params::test_nova_setup,
     circom::GrapevineFCircuit,
+    errors::{GrapevineError, StepKind},
+    params::{r1cs_digest, GrapevineProverParams},
+    proof::{fr_to_u64, GrapevineInstances, GrapevineProof, PROOF_FORMAT_VERSION},
+    utils::{
+        inputs::{
+            get_z0, inputs_to_json, is_fresh_start, reconstruct_inputs, validate_input_sequence,
+            validate_state,
+        },
+        wrapper::CircomPrivateInput,
+        DEGREE_INDEX,
+    },
 };
 
+// Nova instantiated over the pallas/vesta cycle with a given step circuit
+pub type NovaFor<FC> = Nova<
+    Projective,
+    GVar,
+    Projective2,
+    GVar2,
+    FC,
+    Pedersen<Projective>,
+    Pedersen<Projective2>,
+>;
+
+// Nova instantiated over the pallas/vesta cycle with the grapevine circuit
+pub type NOVA = NovaFor<GrapevineFCircuit<Fr>>;
+
+// A step circuit the prover hands each step's private input to before folding it. External
+// inputs are only supported by circuits declaring an `external_input` signal, which the bundled
+// grapevine circuit does not, so for now only the mock circuit accepts a non-empty one
+pub trait GrapevineStepCircuit: FCircuit<Fr> {
+    fn set_private_input(&mut self, input: CircomPrivateInput);
+    fn clear_private_input(&mut self);
+    fn set_external_input(&mut self, external_input: Vec<BigInt>) -> Result<(), GrapevineError>;
+    fn circuit_fingerprint(&self) -> Result<String, GrapevineError>;
+}
+
+impl GrapevineStepCircuit for GrapevineFCircuit<Fr> {
+    fn set_private_input(&mut self, input: CircomPrivateInput) {
+        GrapevineFCircuit::set_private_input(self, input)
+    }
+
+    fn clear_private_input(&mut self) {
+        GrapevineFCircuit::clear_private_input(self)
+    }
+
+    // grapevine.circom declares no external_input signal, so only clearing it is accepted
+    fn set_external_input(&mut self, external_input: Vec<BigInt>) -> Result<(), GrapevineError> {
+        match external_input.is_empty() {
+            true => Ok(()),
+            false => Err(GrapevineError::InvalidInput(
+                "The grapevine circuit declares no external input".to_string(),
+            )),
+        }
+    }
+
+    fn circuit_fingerprint(&self) -> Result<String, GrapevineError> {
+        #[cfg(feature = "prover")]
+        return self.circom_wrapper().circuit_fingerprint();
+        #[cfg(not(feature = "prover"))]
+        Err(GrapevineError::ArtifactError(
+            "Circuit fingerprints require the prover feature".to_string(),
+        ))
+    }
+}
+
+// Drives the folding of a grapevine chain (logic step followed by chaff step per degree)
+#[derive(Clone)]
+pub struct GrapevineProver<FC: GrapevineStepCircuit = GrapevineFCircuit<Fr>> {
+    folding_scheme: NovaFor<FC>,
+    z_0: Vec<Fr>,
+    steps: usize,
+    degree: u64,
+    resumed: bool,
+    checkpoint: Option<(NovaFor<FC>, usize, u64)>,
+    timings: StepTimings,
+}
+
+impl<FC: GrapevineStepCircuit> GrapevineProver<FC> {
+    /**
+     * Initializes a new folding scheme for the grapevine circuit
+     * @dev z_0 is either the all-zero fresh state or the final state of a chain being extended.
+     *      A degree 0 state with any other slot set is malformed and rejected
+     *
+     * @param prover_params - the nova prover params
+     * @param f_circuit - the grapevine function circuit
+     * @param z_0 - the starting ivc state
+     * @return - the prover ready to fold degrees
+     */
+    pub fn new(
+        prover_params: &GrapevineProverParams,
+        f_circuit: FC,
+        z_0: Vec<Fr>,
+    ) -> Result<Self, GrapevineError> {
+        let resumed = !is_fresh_start(&z_0);
+        if resumed && z_0.get(DEGREE_INDEX).copied().unwrap_or_default().is_zero() {
+            return Err(GrapevineError::InvalidInput(
+                "Malformed fresh state: degree 0 state must be all zeros".to_string(),
+            ));
+        }
+        let folding_scheme = NovaFor::<FC>::init(prover_params, f_circuit, z_0.clone())
+            .map_err(|e| GrapevineError::FoldingError(e.to_string()))?;
+        Ok(Self {
+            folding_scheme,
+            z_0,
+            steps: 0,
+            degree: 0,
+            resumed,
+            checkpoint: None,
+            timings: StepTimings::default(),
+        })
+    }
+
+    /**
+     * Folds a single step with the given private input
+     *
+     * @param input - the private input to set on the function circuit before folding
+     */
+    pub fn prove_step(&mut self, input: CircomPrivateInput) -> Result<(), GrapevineError> {
+        let chaff = input.is_chaff();
+        let kind = input.kind();
+        self.folding_scheme.F.set_private_input(input);
+        let start = Instant::now();
+        let folded = self
+            .folding_scheme
+            .prove_step()
+            .map_err(|e| GrapevineError::FoldingError(e.to_string()));
+        let elapsed = start.elapsed();
+        // never leave the secrets of a folded (or failed) step on the circuit
+        self.folding_scheme.F.clear_private_input();
+        folded?;
+        self.timings.record(kind, elapsed);
+        self.steps += 1;
+        if !chaff {
+            self.degree += 1;
+        }
+        Ok(())
+    }
+
+    /**
+     * Folds a single step with its own public external input
+     * @dev the external input is reset afterwards so it cannot leak into the next step. Only
+     *      circuits declaring an external input support this (currently the mock circuit)
+     *
+     * @param input - the private input to set on the function circuit before folding
+     * @param external_input - the external input to set on the function circuit before folding
+     */
+    pub fn prove_step_with_external_input(
+        &mut self,
+        input: CircomPrivateInput,
+        external_input: Vec<BigInt>,
+    ) -> Result<(), GrapevineError> {
+        self.folding_scheme.F.set_external_input(external_input)?;
+        let folded = self.prove_step(input);
+        self.folding_scheme.F.set_external_input(vec![])?;
+        folded
+    }
+
+    /**
+     * Replaces the function circuit folded by the remaining steps
+     * @dev Nova folds every step against the R1CS of a single FCircuit, so the replacement must
+     *      have the same fingerprint (e.g. the same artifacts loaded from bytes or with a witness
+     *      cache). A chain spanning a circuit upgrade cannot be folded in one proof: prove each
+     *      version's segment separately, starting the later segment from the earlier proof's
+     *      final state, and stitch the proofs with `verify_extends`
+     *
+     * @param f_circuit - the function circuit to fold the remaining steps with
+     * @return - CircuitVersionMixed if the circuit differs from the one being folded
+     */
+    pub fn swap_circuit(&mut self, f_circuit: FC) -> Result<(), GrapevineError> {
+        let folding = self.folding_scheme.F.circuit_fingerprint()?;
+        let got = f_circuit.circuit_fingerprint()?;
+        if folding != got {
+            return Err(GrapevineError::CircuitVersionMixed { folding, got });
+        }
+        self.folding_scheme.F = f_circuit;
+        Ok(())
+    }
+
+    /**
+     * Describes a failed step so it can be reproduced without the prover that hit it
+     * @dev the phrase and auth secrets of the input are redacted unless include_secrets
+     *      is set, which should only be done when debugging privately. Usernames are public
+     *
+     * @param input - the private input of the step that failed
+     * @param error - the error folding the step returned
+     * @param include_secrets - whether to leave the secrets of the input in the report
+     * @return - the report for the step after the last one folded
+     */
+    pub fn failure_report(
+        &self,
+        input: &CircomPrivateInput,
+        error: GrapevineError,
+        include_secrets: bool,
+    ) -> FailureReport {
+        let mut steps = inputs_to_json(std::slice::from_ref(input));
+        let mut inputs_redacted = steps[0].take();
+        if !include_secrets {
+            for key in ["phrase", "phrase_bytes", "auth_secrets"] {
+                if let Some(value) = inputs_redacted.get_mut(key) {
+                    redact(value);
+                }
+            }
+        }
+        FailureReport {
+            step: self.steps,
+            error,
+            inputs_redacted,
+            circuit_fingerprint: self.folding_scheme.F.circuit_fingerprint().ok(),
+        }
+    }
+
+    /**
+     * Folds a degree of separation: the logic step followed by a chaff step
+     *
+     * @param input - the private input for the logic step
+     */
+    pub fn add_degree(&mut self, input: CircomPrivateInput) -> Result<(), GrapevineError> {
+        self.prove_step(input)?;
+        self.prove_step(CircomPrivateInput::empty(true))
+    }
+
+    /**
+     * Snapshots the current folding state so it can be restored with `rollback`
+     * @dev overwrites any previous checkpoint
+     */
+    pub fn checkpoint(&mut self) {
+        self.checkpoint = Some((self.folding_scheme.clone(), self.steps, self.degree));
+    }
+
+    /**
+     * Restores the folding state from the last checkpoint, discarding any steps folded since
+     * @dev the checkpoint is kept so the prover can roll back to it again
+     */
+    pub fn rollback(&mut self) -> Result<(), GrapevineError> {
+        let (folding_scheme, steps, degree) = self
+            .checkpoint
+            .as_ref()
+            .ok_or(GrapevineError::NoCheckpoint)?;
+        let discarded = self.steps - *steps;
+        self.timings.steps.truncate(self.timings.steps.len().saturating_sub(discarded));
+        self.folding_scheme = folding_scheme.clone();
+        self.steps = *steps;
+        self.degree = *degree;
+        Ok(())
+    }
+
+    // returns the time each step folded by this prover took (not steps of a loaded state)
+    pub fn timings(&self) -> &StepTimings {
+        &self.timings
+    }
+
+    // returns the number of steps (logic and chaff) folded so far
+    pub fn steps_folded(&self) -> usize {
+        self.steps
+    }
+
+    // returns the number of logic steps (degrees of separation) folded so far
+    pub fn current_degree(&self) -> u64 {
+        self.degree
+    }
+
+    // returns true if the prover extends an existing chain rather than starting a fresh one
+    pub fn is_resumed(&self) -> bool {
+        self.resumed
+    }
+
+    // returns the current ivc state
+    pub fn state(&self) -> Vec<Fr> {
+        self.folding_scheme.state()
+    }
+
+    // returns the underlying folding scheme
+    pub fn folding_scheme(&self) -> &NovaFor<FC> {
+        &self.folding_scheme
+    }
+
+    /**
+     * Estimates the in-memory size of the folding state
+     * @dev measured as the uncompressed serialized size of the running, incoming and cyclefold
+     *      instances and their witnesses (W_i, w_i and cf_W_i, as written by `save_state`). This
+     *      counts every field element and point held by the fold but excludes allocator
+     *      overhead, the function circuit and the prover params
+     *
+     * @return - the estimated size of each part of the folding state in bytes
+     */
+    pub fn memory_report(&self) -> MemoryReport {
+        let nova = &self.folding_scheme;
+        MemoryReport {
+            running_bytes: nova.U_i.uncompressed_size() + witness_size(&nova.W_i),
+            incoming_bytes: nova.u_i.uncompressed_size() + witness_size(&nova.w_i),
+            cyclefold_bytes: nova.cf_U_i.uncompressed_size() + witness_size(&nova.cf_W_i),
+        }
+    }
+
+    /**
+     * Serializes the folding state so proving can be resumed after a restart
+     * @dev includes the running and incoming witnesses, so the output is as secret as the
+     *      chain's inputs. The in-memory checkpoint (see `checkpoint`) is not saved
+     *
+     * @param writer - the destination of the serialized state
+     */
+    pub fn save_state<W: Write>(&self, mut writer: W) -> Result<(), GrapevineError> {
+        let nova = &self.folding_scheme;
+        self.z_0
+            .serialize_compressed(&mut writer)
+            .and_then(|_| (self.steps as u64).serialize_compressed(&mut writer))
+            .and_then(|_| self.degree.serialize_compressed(&mut writer))
+            .and_then(|_| nova.i.serialize_compressed(&mut writer))
+            .and_then(|_| nova.z_i.serialize_compressed(&mut writer))
+            .and_then(|_| serialize_witness(&nova.w_i, &mut writer))
+            .and_then(|_| nova.u_i.serialize_compressed(&mut writer))
+            .and_then(|_| serialize_witness(&nova.W_i, &mut writer))
+            .and_then(|_| nova.U_i.serialize_compressed(&mut writer))
+            .and_then(|_| serialize_witness(&nova.cf_W_i, &mut writer))
+            .and_then(|_| nova.cf_U_i.serialize_compressed(&mut writer))
+            .map_err(|e| GrapevineError::SerializationError(e.to_string()))
+    }
+
+    /**
+     * Restores a prover from state written by `save_state`
+     *
+     * @param prover_params - the nova prover params the state was folded with
+     * @param f_circuit - the step circuit the state was folded with
+     * @param reader - the source of the serialized state
+     * @return - the prover, ready to fold the next step
+     */
+    pub fn load_state<R: Read>(
+        prover_params: &GrapevineProverParams,
+        f_circuit: FC,
+        mut reader: R,
+    ) -> Result<Self, GrapevineError> {
+        let map_err = |e: SerializationError| GrapevineError::SerializationError(e.to_string());
+        let z_0 = Vec::<Fr>::deserialize_compressed(&mut reader).map_err(map_err)?;
+        let steps = u64::deserialize_compressed(&mut reader).map_err(map_err)? as usize;
+        let degree = u64::deserialize_compressed(&mut reader).map_err(map_err)?;
+        let mut prover = Self::new(prover_params, f_circuit, z_0)?;
+        prover.steps = steps;
+        prover.degree = degree;
+        let nova = &mut prover.folding_scheme;
+        nova.i = Fr::deserialize_compressed(&mut reader).map_err(map_err)?;
+        nova.z_i = Vec::<Fr>::deserialize_compressed(&mut reader).map_err(map_err)?;
+        nova.w_i = deserialize_witness(&mut reader).map_err(map_err)?;
+        nova.u_i = CommittedInstance::<Projective>::deserialize_compressed(&mut reader)
+            .map_err(map_err)?;
+        nova.W_i = deserialize_witness(&mut reader).map_err(map_err)?;
+        nova.U_i = CommittedInstance::<Projective>::deserialize_compressed(&mut reader)
+            .map_err(map_err)?;
+        nova.cf_W_i = deserialize_witness(&mut reader).map_err(map_err)?;
+        nova.cf_U_i = CommittedInstance::<Projective2>::deserialize_compressed(&mut reader)
+            .map_err(map_err)?;
+        Ok(prover)
+    }
+
+    /**
+     * Exports the folded chain as a proof that can be verified independently of the prover
+     * @dev a prover that never folded a step returns NoStepsFolded, fresh or resumed. Degree 0
+     *      proofs are not supported either: a fresh chain must fold at least the phrase
+     *      holder's logic step, otherwise the proof asserts nothing and DegreeTooLow is returned
+     *
+     * @return - the proof over all steps folded so far
+     */
+    pub fn finalize(&self) -> Result<GrapevineProof, GrapevineError> {
+        if self.steps == 0 {
+            return Err(GrapevineError::NoStepsFolded);
+        }
+        if !self.resumed && self.degree == 0 {
+            return Err(GrapevineError::DegreeTooLow);
+        }
+        Ok(GrapevineProof {
+            z_0: self.z_0.clone(),
+            z_i: self.state(),
+            num_steps: self.steps,
+            params_digest: r1cs_digest(&self.folding_scheme.r1cs, &self.folding_scheme.cf_r1cs),
+            poseidon_digest: None,
+            instances: GrapevineInstances::from(&self.folding_scheme),
+        })
+    }
+}
+
+// writes a nova witness: the error and witness vectors with their blinding factors
+fn serialize_witness<C: CurveGroup, W: Write>(
+    witness: &Witness<C>,
+    mut writer: W,
+) -> Result<(), SerializationError> {
+    witness.E.serialize_compressed(&mut writer)?;
+    witness.rE.serialize_compressed(&mut writer)?;
+    witness.W.serialize_compressed(&mut writer)?;
+    witness.rW.serialize_compressed(&mut writer)
+}
+
+// returns the uncompressed size of a nova witness, i.e. of what `serialize_witness` writes
+fn witness_size<C: CurveGroup>(witness: &Witness<C>) -> usize {
+    witness.E.uncompressed_size()
+        + witness.rE.uncompressed_size()
+        + witness.W.uncompressed_size()
+        + witness.rW.uncompressed_size()
+}
+
+// reads a nova witness written by `serialize_witness`
+fn deserialize_witness<C: CurveGroup, R: Read>(
+    mut reader: R,
+) -> Result<Witness<C>, SerializationError> {
+    let e = Vec::<C::ScalarField>::deserialize_compressed(&mut reader)?;
+    let r_e = C::ScalarField::deserialize_compressed(&mut reader)?;
+    let w = Vec::<C::ScalarField>::deserialize_compressed(&mut reader)?;
+    let r_w = C::ScalarField::deserialize_compressed(&mut reader)?;
+    Ok(Witness {
+        E: e,
+        rE: r_e,
+        W: w,
+        rW: r_w,
+    })
+}
+
+// Estimated in-memory size of a prover's folding state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub running_bytes: usize,
+    pub incoming_bytes: usize,
+    pub cyclefold_bytes: usize,
+}
+
+impl MemoryReport {
+    // returns the estimated size of the whole folding state in bytes
+    pub fn total(&self) -> usize {
+        self.running_bytes + self.incoming_bytes + self.cyclefold_bytes
+    }
+}
+
+// Progress reported after each degree is folded from a stream
+#[cfg(feature = "async")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoldProgress {
+    pub degree: u64,
+    pub steps_folded: usize,
+}
+
+#[cfg(feature = "async")]
+impl GrapevineProver {
+    /**
+     * Folds each degree's logic input as it arrives on a stream
+     * @dev folding is CPU bound so each degree is folded on the blocking thread pool
+     *
+     * @param stream - the logic step inputs, one per degree
+     * @param progress - channel receiving a progress event after each folded degree
+     * @return - the prover after the stream is exhausted
+     */
+    pub async fn fold_stream<S>(
+        mut self,
+        stream: S,
+        progress: tokio::sync::mpsc::UnboundedSender<FoldProgress>,
+    ) -> Result<Self, GrapevineError>
+    where
+        S: futures::Stream<Item = CircomPrivateInput>,
+    {
+        use futures::StreamExt;
+
+        let mut stream = Box::pin(stream);
+        while let Some(input) = stream.next().await {
+            self = tokio::task::spawn_blocking(move || {
+                self.add_degree(input)?;
+                Ok::<Self, GrapevineError>(self)
+            })
+            .await
+            .map_err(|e| GrapevineError::FoldingError(e.to_string()))??;
+
+            // a dropped receiver only means nobody is listening for progress
+            let _ = progress.send(FoldProgress {
+                degree: self.current_degree(),
+                steps_folded: self.steps_folded(),
+            });
+        }
+        Ok(self)
+    }
+}
+
+/**
+ * Folds a whole chain in one call and exports the folded proof
+ *
+ * @param prover_params - the nova prover params
+ * @param f_circuit - the grapevine function circuit
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @return - the proof over every step of the chain
+ */
+pub fn prove_chain(
+    prover_params: &GrapevineProverParams,
+    f_circuit: GrapevineFCircuit<Fr>,
+    inputs: &[CircomPrivateInput],
+) -> Result<GrapevineProof, GrapevineError> {
+    if inputs.is_empty() {
+        return Err(GrapevineError::InputsEmpty);
+    }
+    let mut prover = GrapevineProver::new(prover_params, f_circuit, get_z0().to_vec())?;
+    for input in inputs {
+        prover.prove_step(input.clone())?;
+    }
+    prover.finalize()
+}
+
+// Wall clock time spent folding each step, tagged as logic or chaff
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StepTimings {
+    pub steps: Vec<(StepKind, Duration)>,
+}
+
+impl StepTimings {
+    // records the time a step of the given kind took to fold
+    pub fn record(&mut self, kind: StepKind, elapsed: Duration) {
+        self.steps.push((kind, elapsed));
+    }
+
+    // returns the total time spent folding steps of the given kind
+    pub fn total_for(&self, kind: StepKind) -> Duration {
+        self.steps
+            .iter()
+            .filter(|(step_kind, _)| *step_kind == kind)
+            .map(|(_, elapsed)| *elapsed)
+            .sum()
+    }
+
+    // returns the total time spent folding steps of any kind
+    pub fn total(&self) -> Duration {
+        self.steps.iter().map(|(_, elapsed)| *elapsed).sum()
+    }
+
+    /**
+     * Computes the share of proving time spent on chaff, i.e. on privacy rather than degrees
+     * @dev with one chaff step per degree this sits near 0.5, since both kinds fold the same
+     *      circuit. Well above that (e.g. a chain padded to a fixed length) the chaff to logic
+     *      ratio is worth tuning
+     *
+     * @return - the fraction of the total time spent on chaff steps, 0 if nothing was timed
+     */
+    pub fn chaff_time_fraction(&self) -> f64 {
+        let total = self.total().as_secs_f64();
+        if total == 0.0 {
+            return 0.0;
+        }
+        self.total_for(StepKind::Chaff).as_secs_f64() / total
+    }
+}
+
+// A shareable reproduction of a step that failed to fold
+#[derive(Clone, Debug, Serialize)]
+pub struct FailureReport {
+    // the index of the step that failed, or the step count if finalizing failed
+    pub step: usize,
+    pub error: GrapevineError,
+    // the failing step's input as JSON (see `inputs_to_json`), null if finalizing failed
+    pub inputs_redacted: serde_json::Value,
+    // the fingerprint of the circuit being folded, if it could be computed
+    pub circuit_fingerprint: Option<String>,
+}
+
+// replaces every non-null value (or element of an array) with a redaction marker
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Null => {}
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => *value = serde_json::Value::String("[redacted]".to_string()),
+    }
+}
+
+/**
+ * Folds a chain, describing the first failure in a report that can be shared for debugging
+ *
+ * @param prover_params - the nova prover params
+ * @param f_circuit - the step circuit
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @param include_secrets - whether the report may carry the failing step's secrets
+ * @return - the proof, or the report of the step that failed
+ */
+pub fn prove_chain_with_report<FC: GrapevineStepCircuit>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    inputs: &[CircomPrivateInput],
+    include_secrets: bool,
+) -> Result<GrapevineProof, FailureReport> {
+    let mut prover = GrapevineProver::new(prover_params, f_circuit, get_z0().to_vec())
+        .map_err(|error| FailureReport {
+            step: 0,
+            error,
+            inputs_redacted: serde_json::Value::Null,
+            circuit_fingerprint: None,
+        })?;
+    for input in inputs {
+        if let Err(error) = prover.prove_step(input.clone()) {
+            return Err(prover.failure_report(input, error, include_secrets));
+        }
+    }
+    prover.finalize().map_err(|error| FailureReport {
+        step: prover.steps_folded(),
+        error,
+        inputs_redacted: serde_json::Value::Null,
+        circuit_fingerprint: prover.folding_scheme.F.circuit_fingerprint().ok(),
+    })
+}
+
+/**
+ * Proves one segment of a chain from an arbitrary starting state, e.g. on its own machine
+ * @dev segments after the first start from the final state of the previous segment's proof
+ *      and are stitched together with `verify_extends`. Each segment must hold whole degrees
+ *      (an even number of steps) so its logic steps stay at even indices
+ *
+ * @param prover_params - the nova prover params, the same for every segment
+ * @param f_circuit - the grapevine function circuit
+ * @param z_start - the state the segment starts from (the fresh state for the first segment)
+ * @param inputs_segment - the interleaved (logic, chaff) step inputs of the segment
+ * @return - the proof of the segment
+ */
+pub fn prove_segment(
+    prover_params: &GrapevineProverParams,
+    f_circuit: GrapevineFCircuit<Fr>,
+    z_start: Vec<Fr>,
+    inputs_segment: &[CircomPrivateInput],
+) -> Result<GrapevineProof, GrapevineError> {
+    if inputs_segment.is_empty() {
+        return Err(GrapevineError::InputsEmpty);
+    }
+    if inputs_segment.len() % 2 != 0 {
+        return Err(GrapevineError::OddStepCount(inputs_segment.len()));
+    }
+    let mut prover = GrapevineProver::new(prover_params, f_circuit, z_start)?;
+    for input in inputs_segment {
+        prover.prove_step(input.clone())?;
+    }
+    prover.finalize()
+}
+
+/**
+ * Proves a chain reproducibly, so an audit can regenerate a byte identical proof
+ * @dev the chaff (and padding) steps are the only randomness in proving: the pedersen
+ *      commitments nova folds with are not blinded. Each is seeded from (chaff_seed, step), so
+ *      the same chain, seed and params always produce the same serialized proof
+ *
+ * @param prover_params - the nova prover params
+ * @param f_circuit - the grapevine function circuit
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @param chaff_seed - the seed every chaff step draws its random values from
+ * @return - the proof over every step of the chain
+ */
+pub fn prove_chain_deterministic(
+    prover_params: &GrapevineProverParams,
+    f_circuit: GrapevineFCircuit<Fr>,
+    inputs: &[CircomPrivateInput],
+    chaff_seed: [u8; 32],
+) -> Result<GrapevineProof, GrapevineError> {
+    let seeded = inputs
+        .iter()
+        .enumerate()
+        .map(|(step, input)| match input.is_chaff() {
+            true => input.clone().with_chaff_seed(chaff_seed, step),
+            false => Ok(input.clone()),
+        })
+        .collect::<Result<Vec<CircomPrivateInput>, GrapevineError>>()?;
+    prove_chain(prover_params, f_circuit, &seeded)
+}
+
+/**
+ * Folds a chain requesting each auth secret just in time, e.g. from a hardware secure element
+ * @dev the callback is called once per degree, in order. Only the current and previous
+ *      degree's secrets are held at any time, and each is dropped once its steps are folded
+ *
+ * @param prover_params - the nova prover params
+ * @param f_circuit - the grapevine function circuit
+ * @param phrase - the secret phrase at the origin of the chain
+ * @param usernames - the username of each degree in order
+ * @param auth_secret - returns the auth secret of the given degree (0-indexed)
+ * @return - the proof over every degree of the chain
+ */
+pub fn prove_chain_with_secret_callback<S>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: GrapevineFCircuit<Fr>,
+    phrase: &String,
+    usernames: &[String],
+    mut auth_secret: S,
+) -> Result<GrapevineProof, GrapevineError>
+where
+    S: FnMut(usize) -> Result<BigInt, GrapevineError>,
+{
+    if usernames.is_empty() {
+        return Err(GrapevineError::DegreeTooLow);
+    }
+    let mut prover = GrapevineProver::new(prover_params, f_circuit, get_z0().to_vec())?;
+    let mut prev: Option<(&String, BigInt)> = None;
+    for (degree, username) in usernames.iter().enumerate() {
+        let secret = auth_secret(degree)?;
+        let input = match prev.take() {
+            None => CircomPrivateInput::new(
+                Some(phrase.clone()),
+                [None, Some(username.clone())],
+                [None, Some(secret.clone())],
+                false,
+            )?,
+            Some((prev_username, prev_secret)) => CircomPrivateInput::new(
+                None,
+                [Some(prev_username.clone()), Some(username.clone())],
+                [Some(prev_secret), Some(secret.clone())],
+                false,
+            )?,
+        };
+        prover.add_degree(input)?;
+        prev = Some((username, secret));
+    }
+    prover.finalize()
+}
+
+/**
+ * Folds a chain durably, checkpointing the folding state to disk every `every_k_steps` steps
+ * @dev if a checkpoint exists the chain resumes from it, so a crash loses at most
+ *      `every_k_steps` steps of work. The checkpoint must have folded a prefix of `inputs`,
+ *      which is checked by replaying that prefix natively. It is removed once the proof is done
+ *
+ * @param prover_params - the nova prover params
+ * @param f_circuit - the grapevine function circuit
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @param checkpoint_path - the file the folding state is checkpointed to
+ * @param every_k_steps - the number of steps folded between checkpoints
+ * @return - the proof over every step of the chain
+ */
+pub fn prove_chain_checkpointed(
+    prover_params: &GrapevineProverParams,
+    f_circuit: GrapevineFCircuit<Fr>,
+    inputs: &[CircomPrivateInput],
+    checkpoint_path: &Path,
+    every_k_steps: usize,
+) -> Result<GrapevineProof, GrapevineError> {
+    if inputs.is_empty() {
+        return Err(GrapevineError::InputsEmpty);
+    }
+    if every_k_steps == 0 {
+        return Err(GrapevineError::InvalidInput(
+            "Must checkpoint at least every step".to_string(),
+        ));
+    }
+    let io_err = |e: std::io::Error| GrapevineError::SerializationError(e.to_string());
+    let mut prover = match File::open(checkpoint_path) {
+        Ok(file) => {
+            let reader = BufReader::new(file);
+            let prover = GrapevineProver::load_state(prover_params, f_circuit.clone(), reader)?;
+            let folded = prover.steps_folded();
+            if folded > inputs.len()
+                || replay_native(&f_circuit, get_z0().to_vec(), &inputs[..folded])?
+                    != prover.state()
+            {
+                return Err(GrapevineError::InvalidInput(
+                    "Checkpoint was not folded from this chain".to_string(),
+                ));
+            }
+            prover
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            GrapevineProver::new(prover_params, f_circuit, get_z0().to_vec())?
+        }
+        Err(e) => return Err(io_err(e)),
+    };
+
+    for input in inputs[prover.steps_folded()..].iter() {
+        prover.prove_step(input.clone())?;
+        if prover.steps_folded() % every_k_steps == 0 {
+            // write aside then rename so a crash mid-write keeps the previous checkpoint
+            let tmp_path = checkpoint_path.with_extension("tmp");
+            let mut writer = BufWriter::new(File::create(&tmp_path).map_err(io_err)?);
+            prover.save_state(&mut writer)?;
+            writer.flush().map_err(io_err)?;
+            std::fs::rename(&tmp_path, checkpoint_path).map_err(io_err)?;
+        }
+    }
+    let proof = prover.finalize()?;
+    match std::fs::remove_file(checkpoint_path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(io_err(e)),
+        _ => Ok(proof),
+    }
+}
+
+// returns the file the witness of a step is precomputed to
+fn witness_path(dir: &Path, step: usize) -> std::path::PathBuf {
+    dir.join(format!("witness_{}.json", step))
+}
+
+/**
+ * Precomputes the witness of every logic step of a chain to its own file
+ * @dev the chain is replayed natively from the fresh state, writing each step's witness out
+ *      (in the `dump_cache` format) before the next is calculated, so at most one witness is
+ *      held in memory. Chaff witnesses are random and never reused, so their files are empty
+ *      and they are calculated when folded (seed the chaff to make the proof reproducible)
+ *
+ * @param f_circuit - the grapevine function circuit
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @param dir - the directory the witness files are written to, created if missing
+ */
+pub fn precompute_witnesses_to_disk(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    inputs: &[CircomPrivateInput],
+    dir: &Path,
+) -> Result<(), GrapevineError> {
+    std::fs::create_dir_all(dir).map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+    // a private cache, so the witnesses never land in the cache of the circuit passed in
+    let mut f_circuit = f_circuit.clone().with_witness_cache();
+    let mut z_i = get_z0().to_vec();
+    for (i, input) in inputs.iter().enumerate() {
+        f_circuit.set_private_input(input.clone());
+        z_i = f_circuit
+            .step_native(i, z_i)
+            .map_err(|e| GrapevineError::FoldingError(e.to_string()))?;
+        f_circuit.circom_wrapper().dump_cache(&witness_path(dir, i))?;
+        f_circuit.circom_wrapper().clear_cache();
+    }
+    Ok(())
+}
+
+/**
+ * Folds a chain whose witnesses were precomputed with `precompute_witnesses_to_disk`
+ * @dev each step's witness is read back just before the step is folded and dropped after, so
+ *      memory stays bounded however long the chain is
+ *
+ * @param prover_params - the nova prover params
+ * @param f_circuit - the grapevine function circuit
+ * @param inputs - the interleaved (logic, chaff) step inputs the witnesses were computed for
+ * @param dir - the directory holding the witness files
+ * @return - the proof over every step of the chain
+ */
+pub fn prove_chain_from_disk(
+    prover_params: &GrapevineProverParams,
+    f_circuit: GrapevineFCircuit<Fr>,
+    inputs: &[CircomPrivateInput],
+    dir: &Path,
+) -> Result<GrapevineProof, GrapevineError> {
+    if inputs.is_empty() {
+        return Err(GrapevineError::InputsEmpty);
+    }
+    let f_circuit = f_circuit.with_witness_cache();
+    let mut prover = GrapevineProver::new(prover_params, f_circuit, get_z0().to_vec())?;
+    for (i, input) in inputs.iter().enumerate() {
+        prover
+            .folding_scheme
+            .F
+            .circom_wrapper_mut()
+            .load_cache(&witness_path(dir, i))?;
+        let folded = prover.prove_step(input.clone());
+        prover.folding_scheme.F.circom_wrapper().clear_cache();
+        folded?;
+    }
+    prover.finalize()
+}
+
+/**
+ * Folds a whole chain where every step carries its own public external input
+ * @dev only circuits declaring an external input support this (currently the mock circuit),
+ *      the grapevine circuit rejects the first step with InvalidInput
+ *
+ * @param prover_params - the nova prover params
+ * @param f_circuit - the step circuit
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @param external_inputs - the external input of each step, aligned with `inputs`
+ * @return - the proof over every step of the chain
+ */
+pub fn prove_chain_with_external_inputs<FC: GrapevineStepCircuit>(
+    prover_params: &GrapevineProverParams,
+    f_circuit: FC,
+    inputs: &[CircomPrivateInput],
+    external_inputs: &[Vec<BigInt>],
+) -> Result<GrapevineProof, GrapevineError> {
+    if inputs.is_empty() {
+        return Err(GrapevineError::InputsEmpty);
+    }
+    if external_inputs.len() != inputs.len() {
+        return Err(GrapevineError::InvalidInput(format!(
+            "Expected an external input for each of the {} steps, got {}",
+            inputs.len(),
+            external_inputs.len()
+        )));
+    }
+    let mut prover = GrapevineProver::new(prover_params, f_circuit, get_z0().to_vec())?;
+    for (input, external_input) in inputs.iter().zip(external_inputs) {
+        prover.prove_step_with_external_input(input.clone(), external_input.clone())?;
+    }
+    prover.finalize()
+}
+
+/**
+ * Proves several independent chains against one shared set of prover params
+ * @dev chains are folded in turn; a chain that fails does not abort the rest of the batch
+ *
+ * @param prover_params - the nova prover params shared by every chain
+ * @param f_circuit - the grapevine function circuit, cloned for each chain
+ * @param chains - the interleaved (logic, chaff) step inputs of each chain
+ * @return - the proof or error of each chain, in the order given
+ */
+pub fn prove_many(
+    prover_params: &GrapevineProverParams,
+    f_circuit: &GrapevineFCircuit<Fr>,
+    chains: &[Vec<CircomPrivateInput>],
+) -> Vec<Result<GrapevineProof, GrapevineError>> {
+    chains
+        .iter()
+        .map(|inputs| prove_chain(prover_params, f_circuit.clone(), inputs))
+        .collect()
+}
+
+// Throughput of a batch of proved chains
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProvingStats {
+    pub chains: usize,
+    pub steps: usize,
+    pub total_time: Duration,
+    pub steps_per_second: f64,
+    pub average_chain_time: Duration,
+}
+
+/**
+ * Proves a batch of chains one after another, timing the whole batch
+ * @dev intended for capacity planning: reports sustained steps per second
+ *
+ * @param prover_params - the nova prover params
+ * @param f_circuit - the grapevine function circuit
+ * @param chains - the interleaved (logic, chaff) step inputs of each chain
+ * @return - a proof per chain and the throughput of the batch
+ */
+pub fn prove_batch_with_stats(
+    prover_params: &GrapevineProverParams,
+    f_circuit: GrapevineFCircuit<Fr>,
+    chains: &[Vec<CircomPrivateInput>],
+) -> Result<(Vec<GrapevineProof>, ProvingStats), GrapevineError> {
+    let start = Instant::now();
+    let proofs = chains
+        .iter()
+        .map(|inputs| prove_chain(prover_params, f_circuit.clone(), inputs))
+        .collect::<Result<Vec<GrapevineProof>, GrapevineError>>()?;
+    let total_time = start.elapsed();
+
+    let steps = chains.iter().map(|inputs| inputs.len()).sum::<usize>();
+    let seconds = total_time.as_secs_f64();
+    let stats = ProvingStats {
+        chains: chains.len(),
+        steps,
+        total_time,
+        steps_per_second: if seconds > 0.0 { steps as f64 / seconds } else { 0.0 },
+        average_chain_time: total_time.div_f64(chains.len().max(1) as f64),
+    };
+    Ok((proofs, stats))
+}
+
+// Public inputs of the folded pallas instances (the hash of the ivc state and of the cyclefold
+// instance) and of the vesta cyclefold instance
+const INSTANCE_PUBLIC_INPUTS: usize = 2;
+const CYCLEFOLD_PUBLIC_INPUTS: usize = 7;
+
+// Compressed size of a committed instance: cmE, u, cmW and the length prefixed public inputs
+fn committed_instance_bytes<C: CurveGroup>(public_inputs: usize) -> usize {
+    let point = C::zero().compressed_size();
+    let scalar = C::ScalarField::zero().compressed_size();
+    2 * point + scalar + 0u64.compressed_size() + public_inputs * scalar
+}
+
+// What proving a chain would produce and cost, computed without folding
+#[derive(Clone, Debug, PartialEq)]
+pub struct DryRunReport {
+    pub steps: usize,
+    pub degree: u64,
+    pub final_state: Vec<Fr>,
+    // None until calibrated, as the folding rate depends on the machine
+    pub estimated_proving_time: Option<Duration>,
+    pub estimated_proof_bytes: usize,
+}
+
+impl DryRunReport {
+    // estimates the proving time from the throughput measured by `prove_batch_with_stats`
+    pub fn calibrate(&mut self, stats: &ProvingStats) {
+        if stats.steps_per_second > 0.0 {
+            self.estimated_proving_time =
+                Some(Duration::from_secs_f64(self.steps as f64 / stats.steps_per_second));
+        }
+    }
+}
+
+/**
+ * Preflights a chain: validates the inputs and reports the cost of proving them
+ * @dev only computes steps natively, so is far cheaper than folding. The proof size is that of
+ *      `GrapevineProof::to_bytes`, the proving time is only estimated once calibrated
+ *
+ * @param f_circuit - the grapevine function circuit
+ * @param z_0 - the starting ivc state
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @return - the final state and estimated cost, or the reason the chain cannot be proven
+ */
+pub fn dry_run(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    z_0: Vec<Fr>,
+    inputs: &[CircomPrivateInput],
+) -> Result<DryRunReport, GrapevineError> {
+    if inputs.is_empty() {
+        return Err(GrapevineError::InputsEmpty);
+    }
+    validate_input_sequence(inputs)?;
+    let final_state = replay_native(f_circuit, z_0.clone(), inputs)?;
+    validate_state(&final_state)?;
+    let degree = fr_to_u64(final_state[DEGREE_INDEX])?;
+
+    // sized as `GrapevineProof::serialize` writes it: the format version, z_0, z_i, the step
+    // count, params digest and (absent) poseidon digest, then the running, incoming and
+    // cyclefold instances
+    let state_bytes = PROOF_FORMAT_VERSION.compressed_size()
+        + z_0.compressed_size()
+        + final_state.compressed_size()
+        + (inputs.len() as u64).compressed_size()
+        + [0u8; 32].compressed_size()
+        + None::<[u8; 32]>.compressed_size();
+    let instances_bytes = 2 * committed_instance_bytes::<Projective>(INSTANCE_PUBLIC_INPUTS)
+        + committed_instance_bytes::<Projective2>(CYCLEFOLD_PUBLIC_INPUTS);
+    Ok(DryRunReport {
+        steps: inputs.len(),
+        degree,
+        final_state,
+        estimated_proving_time: None,
+        estimated_proof_bytes: state_bytes + instances_bytes,
+    })
+}
+
+/**
+ * Replays a chain natively (no folding) to compute the final state it should produce
+ *
+ * @param f_circuit - the grapevine function circuit
+ * @param z_0 - the starting ivc state
+ * @param inputs - the interleaved step inputs
+ * @return - the final ivc state
+ */
+pub fn replay_native(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    z_0: Vec<Fr>,
+    inputs: &[CircomPrivateInput],
+) -> Result<Vec<Fr>, GrapevineError> {
+    let trace = replay_native_with_trace(f_circuit, z_0.clone(), inputs)?;
+    Ok(trace.last().cloned().unwrap_or(z_0))
+}
+
+/**
+ * Replays a chain natively, recording the state after every step (logic and chaff)
+ *
+ * @param f_circuit - the grapevine function circuit
+ * @param z_0 - the starting ivc state
+ * @param inputs - the interleaved step inputs
+ * @return - the ivc state after each step, one per input
+ */
+pub fn replay_native_with_trace(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    z_0: Vec<Fr>,
+    inputs: &[CircomPrivateInput],
+) -> Result<Vec<Vec<Fr>>, GrapevineError> {
+    let mut f_circuit = f_circuit.clone();
+    let mut z_i = z_0;
+    let mut trace = Vec::with_capacity(inputs.len());
+    for (i, input) in inputs.iter().enumerate() {
+        f_circuit.set_private_input(input.clone());
+        z_i = f_circuit
+            .step_native(i, z_i)
+            .map_err(|e| GrapevineError::FoldingError(e.to_string()))?;
+        trace.push(z_i.clone());
+    }
+    Ok(trace)
+}
+
+/**
+ * Rebuilds a chain's step inputs from its secrets, checking them against a recorded transcript
+ * @dev lets a prover that kept only the transcript and secrets re-prove under new params
+ *
+ * @param f_circuit - the grapevine function circuit
+ * @param phrase - the secret phrase at the origin of the chain
+ * @param path - the (username, auth secret) of each degree in the chain
+ * @param trace - the recorded state after every step, from `replay_native_with_trace`
+ * @return - the interleaved step inputs, or the first step whose state diverges
+ */
+pub fn inputs_from_transcript(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    phrase: &String,
+    path: &[(String, BigInt)],
+    trace: &[Vec<Fr>],
+) -> Result<Vec<CircomPrivateInput>, GrapevineError> {
+    let inputs = reconstruct_inputs(phrase, path)?;
+    if trace.len() != inputs.len() {
+        return Err(GrapevineError::InvalidInput(format!(
+            "Transcript has {} states but the secrets produce {} steps",
+            trace.len(),
+            inputs.len()
+        )));
+    }
+    let replayed = replay_native_with_trace(f_circuit, get_z0().to_vec(), &inputs)?;
+    match replayed.iter().zip(trace).position(|(replayed, recorded)| replayed != recorded) {
+        Some(step) => Err(GrapevineError::StateMismatch { step }),
+        None => Ok(inputs),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::params::test_nova_setup;
+    use crate::proof::{verify, verify_extends};
+    use crate::test_utils::{degree_input, sample_circuit, MockFCircuit, AUTH_SECRETS, USERNAMES};
+    use crate::test_utils::{sample_chain, sample_path, PHRASE};
+    use crate::utils::{inputs::pad_chain_to, CHAFF_INDEX};
+    use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
+    use ark_relations::r1cs::ConstraintSystem;
     use std::path::PathBuf;
-    use std::time::Instant;
+
     #[test]
     fn test_generate_params() {
-        let r1cs_path = PathBuf::from("./circom/artifacts/circuit.r1cs");
-        let wasm_path = PathBuf::from("./circom/artifacts/circuit.wasm");
+        let r1cs_path = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+        let wasm_path = PathBuf::from("./circom/artifacts/grapevine.wasm");
         let f_circuit = GrapevineFCircuit::<Fr>::new((r1cs_path, wasm_path));
         let pre = Instant::now();
         let (prover_params, verifier_params) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit);
@@ -43,4 +1138,701 @@ mod test {
 
         println!("Time to generate params: {:?}", pre.elapsed());
     }
+
+    #[test]
+    fn test_checkpoint_rollback() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let z_0 = get_z0::<Fr>().to_vec();
+        let mut prover = GrapevineProver::new(&prover_params, f_circuit, z_0.clone()).unwrap();
+
+        // rolling back without a checkpoint fails
+        assert!(matches!(
+            prover.rollback(),
+            Err(GrapevineError::NoCheckpoint)
+        ));
+
+        // fold degree 1 (steps 0, 1) and checkpoint
+        prover.add_degree(degree_input(1)).unwrap();
+        prover.checkpoint();
+        let checkpoint_state = prover.state();
+
+        // fold degree 2 (steps 2, 3) with the wrong username then roll back
+        let wrong_input = CircomPrivateInput::new(
+            None,
+            [Some(USERNAMES[0].clone()), Some(USERNAMES[3].clone())],
+            [Some(AUTH_SECRETS[0].clone()), Some(AUTH_SECRETS[3].clone())],
+            false,
+        )
+        .unwrap();
+        prover.add_degree(wrong_input).unwrap();
+        prover.rollback().unwrap();
+        assert_eq!(prover.state(), checkpoint_state);
+        assert_eq!(prover.steps_folded(), 2);
+
+        // fold the intended degree 2 and verify the folded proof
+        prover.add_degree(degree_input(2)).unwrap();
+        GrapevineInstances::from(prover.folding_scheme())
+            .verify(&verifier_params, z_0, prover.state(), Fr::from(4u32))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_fresh_and_resumed_start() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let prover =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), get_z0().to_vec()).unwrap();
+        assert!(!prover.is_resumed());
+
+        // extending a degree 2 chain
+        let z_0 = vec![Fr::from(2), Fr::from(11), Fr::from(12), Fr::from(0)];
+        let prover = GrapevineProver::new(&prover_params, f_circuit.clone(), z_0).unwrap();
+        assert!(prover.is_resumed());
+
+        // a degree 0 state carrying a phrase hash is malformed
+        let z_0 = vec![Fr::from(0), Fr::from(11), Fr::from(0), Fr::from(0)];
+        assert!(matches!(
+            GrapevineProver::new(&prover_params, f_circuit, z_0),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_steps_folded() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0::<Fr>().to_vec()).unwrap();
+        assert_eq!(prover.steps_folded(), 0);
+        assert_eq!(prover.current_degree(), 0);
+
+        // each degree folds a logic and a chaff step
+        prover.add_degree(degree_input(1)).unwrap();
+        assert_eq!(prover.steps_folded(), 2);
+        assert_eq!(prover.current_degree(), 1);
+        prover.add_degree(degree_input(2)).unwrap();
+        assert_eq!(prover.steps_folded(), 4);
+        assert_eq!(prover.current_degree(), 2);
+    }
+
+    #[test]
+    fn test_degree_too_low() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+
+        // a chain with no degrees cannot be built
+        assert!(matches!(
+            reconstruct_inputs(&PHRASE, &[]),
+            Err(GrapevineError::DegreeTooLow)
+        ));
+
+        // nor proven from padding alone
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), get_z0().to_vec()).unwrap();
+        prover.prove_step(CircomPrivateInput::padding()).unwrap();
+        prover.prove_step(CircomPrivateInput::padding()).unwrap();
+        assert!(matches!(prover.finalize(), Err(GrapevineError::DegreeTooLow)));
+
+        // the phrase holder alone is the smallest provable chain
+        let inputs = reconstruct_inputs(&PHRASE, &sample_path(1)).unwrap();
+        let proof = prove_chain(&prover_params, f_circuit, &inputs).unwrap();
+        assert_eq!(proof.num_steps, 2);
+        crate::proof::verify(&verifier_params, &proof).unwrap();
+    }
+
+    #[test]
+    fn test_no_steps_folded() {
+        let f_circuit = MockFCircuit::new(());
+        let (prover_params, _) = test_nova_setup(f_circuit.clone());
+
+        // a fresh prover that never stepped has nothing to finalize
+        let prover =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), get_z0().to_vec()).unwrap();
+        assert!(matches!(prover.finalize(), Err(GrapevineError::NoStepsFolded)));
+
+        // nor does a resumed one, even though its starting state has a degree
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        let resumed = GrapevineProver::new(&prover_params, f_circuit, prover.state()).unwrap();
+        assert!(resumed.is_resumed());
+        assert!(matches!(resumed.finalize(), Err(GrapevineError::NoStepsFolded)));
+    }
+
+    #[test]
+    fn test_swap_circuit() {
+        let f_circuit = MockFCircuit::new(());
+        let (prover_params, _) = test_nova_setup(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+
+        // the same circuit can be swapped in mid-fold
+        prover.swap_circuit(f_circuit.clone()).unwrap();
+        prover.add_degree(degree_input(2)).unwrap();
+
+        // an upgraded circuit cannot, and the fold carries on with the original
+        let upgraded = f_circuit.with_version(2);
+        assert!(matches!(
+            prover.swap_circuit(upgraded),
+            Err(GrapevineError::CircuitVersionMixed { folding, got })
+                if folding == "mock-v1" && got == "mock-v2"
+        ));
+        prover.add_degree(degree_input(3)).unwrap();
+        let proof = prover.finalize().unwrap();
+        assert_eq!(proof.num_steps, 6);
+    }
+
+    #[test]
+    fn test_prove_chain_with_report() {
+        let f_circuit = MockFCircuit::new(());
+        let (prover_params, _) = test_nova_setup(f_circuit.clone());
+        let (inputs, _) = sample_chain(3);
+        prove_chain_with_report(&prover_params, f_circuit.clone(), &inputs, false).unwrap();
+
+        // the logic step of degree 2 fails, with its secrets redacted by default
+        let failing = f_circuit.with_failure_at(2);
+        let report =
+            prove_chain_with_report(&prover_params, failing.clone(), &inputs, false).unwrap_err();
+        assert_eq!(report.step, 2);
+        assert!(matches!(report.error, GrapevineError::FoldingError(_)));
+        assert_eq!(report.circuit_fingerprint.as_deref(), Some("mock-v1"));
+        let redacted = &report.inputs_redacted;
+        assert_eq!(redacted["usernames"][1], serde_json::json!(USERNAMES[1]));
+        assert_eq!(redacted["auth_secrets"][0], serde_json::json!("[redacted]"));
+        assert_eq!(redacted["auth_secrets"][1], serde_json::json!("[redacted]"));
+        assert!(!serde_json::to_string(&report).unwrap().contains(&AUTH_SECRETS[1].to_string()));
+
+        // secrets are only kept when asked for
+        let report = prove_chain_with_report(&prover_params, failing, &inputs, true).unwrap_err();
+        assert_eq!(
+            report.inputs_redacted["auth_secrets"][1],
+            serde_json::json!(AUTH_SECRETS[1].to_string())
+        );
+    }
+
+    #[test]
+    fn test_chaff_time_fraction() {
+        let mut timings = StepTimings::default();
+        assert_eq!(timings.chaff_time_fraction(), 0.0);
+
+        // 650ms of a 1s chain is chaff
+        timings.record(StepKind::Logic, Duration::from_millis(200));
+        timings.record(StepKind::Chaff, Duration::from_millis(250));
+        timings.record(StepKind::Logic, Duration::from_millis(150));
+        timings.record(StepKind::Chaff, Duration::from_millis(400));
+        assert_eq!(timings.total(), Duration::from_secs(1));
+        assert_eq!(timings.total_for(StepKind::Logic), Duration::from_millis(350));
+        assert!((timings.chaff_time_fraction() - 0.65).abs() < 1e-9);
+
+        // the prover tags every step it folds, and forgets those it rolls back
+        let f_circuit = MockFCircuit::new(());
+        let (prover_params, _) = test_nova_setup(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        prover.checkpoint();
+        prover.add_degree(degree_input(2)).unwrap();
+        prover.rollback().unwrap();
+        let kinds = prover.timings().steps.iter().map(|(kind, _)| *kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![StepKind::Logic, StepKind::Chaff]);
+        let fraction = prover.timings().chaff_time_fraction();
+        assert!((0.0..=1.0).contains(&fraction));
+    }
+
+    #[test]
+    fn test_prove_chain_deterministic() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(2);
+        let inputs = pad_chain_to(inputs, 6).unwrap();
+        let seed = [7u8; 32];
+
+        // the same chain and seed prove to the same bytes, padding included
+        let first =
+            prove_chain_deterministic(&prover_params, f_circuit.clone(), &inputs, seed).unwrap();
+        let second = prove_chain_deterministic(&prover_params, f_circuit, &inputs, seed).unwrap();
+        assert_eq!(first.to_bytes().unwrap(), second.to_bytes().unwrap());
+        assert_eq!(first.num_steps, 6);
+
+        // logic steps have no randomness to seed
+        assert!(inputs[0].clone().with_chaff_seed(seed, 0).is_err());
+    }
+
+    #[test]
+    fn test_prove_segment() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, z_0) = sample_chain(3);
+
+        // each segment is proven by its own prover, the second from the first's final state
+        let first =
+            prove_segment(&prover_params, f_circuit.clone(), z_0.to_vec(), &inputs[..2]).unwrap();
+        let second =
+            prove_segment(&prover_params, f_circuit.clone(), first.z_i.clone(), &inputs[2..])
+                .unwrap();
+        verify(&verifier_params, &first).unwrap();
+        verify(&verifier_params, &second).unwrap();
+        verify_extends(&first, &second).unwrap();
+        assert_eq!(second.z_i, replay_native(&f_circuit, z_0.to_vec(), &inputs).unwrap());
+
+        // a segment proven from the wrong state does not continue the chain
+        let z_other = vec![Fr::from(5), Fr::from(11), Fr::from(12), Fr::from(0)];
+        let detached =
+            prove_segment(&prover_params, f_circuit.clone(), z_other, &inputs[2..]).unwrap();
+        assert!(matches!(
+            verify_extends(&first, &detached),
+            Err(GrapevineError::ChainDiscontinuity(_))
+        ));
+
+        // segments hold whole degrees
+        assert!(matches!(
+            prove_segment(&prover_params, f_circuit, first.z_i.clone(), &inputs[2..5]),
+            Err(GrapevineError::OddStepCount(3))
+        ));
+    }
+
+    #[test]
+    fn test_memory_report() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        let report = prover.memory_report();
+        let nova = prover.folding_scheme();
+        assert!(report.running_bytes > nova.W_i.W.uncompressed_size());
+        assert!(report.incoming_bytes > nova.w_i.W.uncompressed_size());
+        assert!(report.cyclefold_bytes > nova.cf_W_i.W.uncompressed_size());
+
+        // the report covers everything the saved state holds besides the ivc states and counters
+        let mut saved = Vec::new();
+        prover.save_state(&mut saved).unwrap();
+        let header = prover.z_0.compressed_size()
+            + (prover.steps as u64).compressed_size()
+            + prover.degree.compressed_size()
+            + nova.i.compressed_size()
+            + nova.z_i.compressed_size();
+        assert!(report.total() >= saved.len() - header);
+    }
+
+    #[test]
+    fn test_reconstruct_inputs() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+
+        // prove a degree 2 chain
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        prover.add_degree(degree_input(2)).unwrap();
+        let proof = prover.finalize().unwrap();
+        crate::proof::verify(&verifier_params, &proof).unwrap();
+
+        // rebuild the inputs from the secrets and replay them to the proven state
+        let inputs = reconstruct_inputs(&PHRASE, &sample_path(2)).unwrap();
+        assert_eq!(inputs.len(), proof.num_steps);
+        let z_i = replay_native(&f_circuit, proof.z_0.clone(), &inputs).unwrap();
+        assert_eq!(z_i, proof.z_i);
+    }
+
+    #[test]
+    fn test_prove_chain() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+
+        // no steps to fold
+        assert!(matches!(
+            prove_chain(&prover_params, f_circuit.clone(), &[]),
+            Err(GrapevineError::InputsEmpty)
+        ));
+
+        // fold a degree 2 chain in one call
+        let inputs = reconstruct_inputs(&PHRASE, &sample_path(2)).unwrap();
+        let proof = prove_chain(&prover_params, f_circuit, &inputs).unwrap();
+        assert_eq!(proof.num_steps, 4);
+        crate::proof::verify(&verifier_params, &proof).unwrap();
+    }
+
+    #[test]
+    fn test_prove_batch_with_stats() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let chains = vec![
+            reconstruct_inputs(&PHRASE, &sample_path(1)).unwrap(),
+            reconstruct_inputs(&PHRASE, &sample_path(2)).unwrap(),
+        ];
+        let (proofs, stats) = prove_batch_with_stats(&prover_params, f_circuit, &chains).unwrap();
+
+        // one verifying proof per chain
+        assert_eq!(proofs.len(), 2);
+        for proof in proofs.iter() {
+            crate::proof::verify(&verifier_params, proof).unwrap();
+        }
+
+        // the stats describe the 6 folded steps
+        assert_eq!(stats.chains, 2);
+        assert_eq!(stats.steps, 6);
+        assert!(stats.total_time > Duration::ZERO);
+        assert!(stats.steps_per_second > 0.0);
+        assert_eq!(stats.average_chain_time, stats.total_time / 2);
+    }
+
+    #[test]
+    fn test_prove_many() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let chains = (1..=3)
+            .map(|degree| reconstruct_inputs(&PHRASE, &sample_path(degree)).unwrap())
+            .collect::<Vec<_>>();
+        let proofs = prove_many(&prover_params, &f_circuit, &chains);
+
+        // every chain proves and verifies against the shared params
+        assert_eq!(proofs.len(), 3);
+        for (inputs, proof) in chains.iter().zip(proofs) {
+            let proof = proof.unwrap();
+            crate::proof::verify(&verifier_params, &proof).unwrap();
+            assert_eq!(proof.num_steps, inputs.len());
+            assert_eq!(proof.z_i, replay_native(&f_circuit, get_z0().to_vec(), inputs).unwrap());
+        }
+
+        // a failing chain is reported in place without aborting the others
+        let mixed = vec![chains[0].clone(), vec![], chains[1].clone()];
+        let proofs = prove_many(&prover_params, &f_circuit, &mixed);
+        assert!(proofs[0].is_ok());
+        assert!(matches!(proofs[1], Err(GrapevineError::InputsEmpty)));
+        assert!(proofs[2].is_ok());
+    }
+
+    #[test]
+    fn test_prove_chain_with_external_inputs() {
+        let f_circuit = MockFCircuit::new(());
+        let (prover_params, verifier_params) = test_nova_setup(f_circuit.clone());
+        let inputs = reconstruct_inputs(&PHRASE, &sample_path(2)).unwrap();
+        let external_inputs = (0..4u64)
+            .map(|step| vec![BigInt::from(1_700_000_000 + step)])
+            .collect::<Vec<_>>();
+
+        // each step sees its own external input, which the mock records in its last slot
+        let proof = prove_chain_with_external_inputs(
+            &prover_params,
+            f_circuit.clone(),
+            &inputs,
+            &external_inputs,
+        )
+        .unwrap();
+        crate::proof::verify(&verifier_params, &proof).unwrap();
+        assert_eq!(proof.z_i[DEGREE_INDEX], Fr::from(2));
+        assert_eq!(proof.z_i[CHAFF_INDEX], Fr::from(1_700_000_003u64));
+
+        // every step needs an external input
+        assert!(matches!(
+            prove_chain_with_external_inputs(
+                &prover_params,
+                f_circuit,
+                &inputs,
+                &external_inputs[..3]
+            ),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+
+        // the grapevine circuit declares no external input signal
+        let mut grapevine_circuit = sample_circuit();
+        assert!(matches!(
+            GrapevineStepCircuit::set_external_input(&mut grapevine_circuit, vec![BigInt::from(1)]),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+        assert!(GrapevineStepCircuit::set_external_input(&mut grapevine_circuit, vec![]).is_ok());
+    }
+
+    #[test]
+    fn test_prove_chain_from_disk() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(2);
+        // seeded chaff so both proofs fold exactly the same witnesses
+        let inputs = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(step, input)| match input.is_chaff() {
+                true => input.with_chaff_seed([3u8; 32], step).unwrap(),
+                false => input,
+            })
+            .collect::<Vec<CircomPrivateInput>>();
+        let dir = std::env::temp_dir().join("grapevine_precomputed_witnesses");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        precompute_witnesses_to_disk(&f_circuit, &inputs, &dir).unwrap();
+        assert!((0..inputs.len()).all(|step| witness_path(&dir, step).exists()));
+        let from_disk =
+            prove_chain_from_disk(&prover_params, f_circuit.clone(), &inputs, &dir).unwrap();
+        let in_memory = prove_chain(&prover_params, f_circuit.clone(), &inputs).unwrap();
+        assert_eq!(from_disk.to_bytes().unwrap(), in_memory.to_bytes().unwrap());
+
+        // every step needs its witness file
+        std::fs::remove_file(witness_path(&dir, 2)).unwrap();
+        assert!(matches!(
+            prove_chain_from_disk(&prover_params, f_circuit, &inputs, &dir),
+            Err(GrapevineError::SerializationError(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prove_chain_checkpointed() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let inputs = reconstruct_inputs(&PHRASE, &sample_path(2)).unwrap();
+        let path = std::env::temp_dir().join("grapevine_prove_checkpointed.bin");
+        let _ = std::fs::remove_file(&path);
+
+        // crash at step 2, after the first degree was checkpointed
+        let mut crashing = inputs[..2].to_vec();
+        crashing.push(CircomPrivateInput::empty(true));
+        assert!(
+            prove_chain_checkpointed(&prover_params, f_circuit.clone(), &crashing, &path, 2)
+                .is_err()
+        );
+        let file = std::fs::File::open(&path).unwrap();
+        let checkpoint =
+            GrapevineProver::load_state(&prover_params, f_circuit.clone(), file).unwrap();
+        assert_eq!(checkpoint.steps_folded(), 2);
+        assert_eq!(checkpoint.current_degree(), 1);
+
+        // a checkpoint that folded past the end of the chain is not resumed
+        assert!(matches!(
+            prove_chain_checkpointed(&prover_params, f_circuit.clone(), &inputs[..1], &path, 2),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+
+        // restarting resumes from the checkpoint and the finished proof verifies
+        let proof =
+            prove_chain_checkpointed(&prover_params, f_circuit, &inputs, &path, 2).unwrap();
+        assert_eq!(proof.num_steps, 4);
+        crate::proof::verify(&verifier_params, &proof).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_prove_chain_with_secret_callback() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let path = sample_path(3);
+        let usernames = path.iter().map(|(username, _)| username.clone()).collect::<Vec<_>>();
+
+        // secrets are requested one degree at a time, in order
+        let mut requested = vec![];
+        let proof = prove_chain_with_secret_callback(
+            &prover_params,
+            f_circuit.clone(),
+            &PHRASE,
+            &usernames,
+            |degree| {
+                requested.push(degree);
+                Ok(path[degree].1.clone())
+            },
+        )
+        .unwrap();
+        assert_eq!(requested, vec![0, 1, 2]);
+        crate::proof::verify(&verifier_params, &proof).unwrap();
+
+        // the proof claims the same chain as proving with every secret in memory
+        let inputs = reconstruct_inputs(&PHRASE, &path).unwrap();
+        let in_memory = prove_chain(&prover_params, f_circuit.clone(), &inputs).unwrap();
+        assert!(proof.same_claim(&in_memory));
+
+        // a secret the callback cannot provide aborts the chain
+        let res = prove_chain_with_secret_callback(
+            &prover_params,
+            f_circuit,
+            &PHRASE,
+            &usernames,
+            |degree| match degree {
+                0 => Ok(path[0].1.clone()),
+                _ => Err(GrapevineError::InvalidInput("Secure element locked".to_string())),
+            },
+        );
+        assert!(matches!(res, Err(GrapevineError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let f_circuit = sample_circuit();
+        let inputs = reconstruct_inputs(&PHRASE, &sample_path(2)).unwrap();
+        let report = dry_run(&f_circuit, get_z0().to_vec(), &inputs).unwrap();
+        assert_eq!(report.steps, 4);
+        assert_eq!(
+            report.final_state,
+            replay_native(&f_circuit, get_z0().to_vec(), &inputs).unwrap()
+        );
+        assert_eq!(report.estimated_proving_time, None);
+
+        // the size estimate is exactly that of the serialized proof
+        let (prover_params, _) = test_nova_setup(f_circuit.clone());
+        let proof = prove_chain(&prover_params, f_circuit.clone(), &inputs).unwrap();
+        assert_eq!(report.estimated_proof_bytes, proof.to_bytes().unwrap().len());
+
+        // calibrating against measured stats fills in the time estimate
+        let mut calibrated = report.clone();
+        calibrated.calibrate(&ProvingStats {
+            chains: 1,
+            steps: 8,
+            total_time: Duration::from_secs(4),
+            steps_per_second: 2.0,
+            average_chain_time: Duration::from_secs(4),
+        });
+        assert_eq!(calibrated.estimated_proving_time, Some(Duration::from_secs(2)));
+
+        // a malformed chain fails the dry run naming the bad step
+        let mut malformed = inputs.clone();
+        malformed.remove(1);
+        assert!(matches!(
+            dry_run(&f_circuit, get_z0().to_vec(), &malformed),
+            Err(GrapevineError::InvalidSequence { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_inputs_from_transcript() {
+        let f_circuit = sample_circuit();
+        let path = sample_path(2);
+        let inputs = reconstruct_inputs(&PHRASE, &path).unwrap();
+        let trace = replay_native_with_trace(&f_circuit, get_z0().to_vec(), &inputs).unwrap();
+
+        // inputs -> trace -> inputs reproduces the same trace
+        let rebuilt = inputs_from_transcript(&f_circuit, &PHRASE, &path, &trace).unwrap();
+        assert_eq!(rebuilt.len(), inputs.len());
+        assert_eq!(
+            replay_native_with_trace(&f_circuit, get_z0().to_vec(), &rebuilt).unwrap(),
+            trace
+        );
+
+        // a tampered transcript is rejected at the step it diverges
+        let mut tampered = trace.clone();
+        tampered[2][DEGREE_INDEX] += Fr::from(1);
+        assert!(matches!(
+            inputs_from_transcript(&f_circuit, &PHRASE, &path, &tampered),
+            Err(GrapevineError::StateMismatch { step: 2 })
+        ));
+
+        // a transcript of a different length is rejected
+        assert!(inputs_from_transcript(&f_circuit, &PHRASE, &path, &trace[..2]).is_err());
+    }
+
+    #[test]
+    fn test_prover_with_mock_circuit() {
+        let f_circuit = MockFCircuit::new(());
+        let (prover_params, verifier_params) = test_nova_setup(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+
+        // each degree folds a logic and a chaff step, only the logic step counts
+        for degree in 1..=3 {
+            prover.add_degree(degree_input(degree)).unwrap();
+        }
+        assert_eq!(prover.steps_folded(), 6);
+        assert_eq!(prover.current_degree(), 3);
+        assert_eq!(prover.state()[DEGREE_INDEX], Fr::from(3));
+        crate::proof::verify(&verifier_params, &prover.finalize().unwrap()).unwrap();
+
+        // rolling back discards steps folded since the checkpoint
+        prover.checkpoint();
+        prover.add_degree(degree_input(4)).unwrap();
+        assert_eq!(prover.current_degree(), 4);
+        prover.rollback().unwrap();
+        assert_eq!(prover.current_degree(), 3);
+        assert_eq!(prover.state()[DEGREE_INDEX], Fr::from(3));
+
+        // the input is cleared after each fold so a stale step cannot be refolded
+        let mut prover =
+            GrapevineProver::new(&prover_params, MockFCircuit::new(()), get_z0().to_vec())
+                .unwrap();
+        prover.prove_step(degree_input(1)).unwrap();
+        let mut f_circuit = prover.folding_scheme().F.clone();
+        assert!(f_circuit.step_native(1, prover.state()).is_err());
+        f_circuit.set_private_input(CircomPrivateInput::empty(true));
+        assert_eq!(f_circuit.step_native(1, prover.state()).unwrap(), prover.state());
+    }
+
+    #[test]
+    fn test_mock_circuit_step_shape() {
+        // synthesizes a step and returns its constraint matrices
+        let matrices = |f_circuit: &MockFCircuit| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_i = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(get_z0::<Fr>().to_vec()))
+                .unwrap();
+            f_circuit.generate_step_constraints(cs.clone(), 0, z_i).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            cs.finalize();
+            cs.to_matrices().unwrap()
+        };
+
+        // logic, chaff and shape-only (no input) steps synthesize the same R1CS
+        let mut logic = MockFCircuit::new(());
+        logic.set_private_input(degree_input(1));
+        let mut chaff = MockFCircuit::new(());
+        chaff.set_private_input(CircomPrivateInput::empty(true));
+        let shape = matrices(&MockFCircuit::new(()));
+        for f_circuit in [logic, chaff] {
+            let step = matrices(&f_circuit);
+            assert_eq!(step.a, shape.a);
+            assert_eq!(step.b, shape.b);
+            assert_eq!(step.c, shape.c);
+        }
+    }
+
+    #[test]
+    fn test_replay_native_with_trace() {
+        let f_circuit = sample_circuit();
+        let inputs = reconstruct_inputs(&PHRASE, &sample_path(3)).unwrap();
+        let trace = replay_native_with_trace(&f_circuit, get_z0().to_vec(), &inputs).unwrap();
+
+        // one state per step, ending at the replayed final state
+        assert_eq!(trace.len(), inputs.len());
+        let z_i = replay_native(&f_circuit, get_z0().to_vec(), &inputs).unwrap();
+        assert_eq!(trace.last().unwrap(), &z_i);
+
+        // no steps leave the starting state untouched
+        assert!(replay_native_with_trace(&f_circuit, get_z0().to_vec(), &[]).unwrap().is_empty());
+        assert_eq!(replay_native(&f_circuit, get_z0().to_vec(), &[]).unwrap(), get_z0().to_vec());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_fold_stream() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+
+        // drive a degree 2 stream to completion
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let stream = futures::stream::iter(vec![degree_input(1), degree_input(2)]);
+        let prover = prover.fold_stream(stream, tx).await.unwrap();
+
+        // one progress event per degree
+        let mut events = vec![];
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                FoldProgress { degree: 1, steps_folded: 2 },
+                FoldProgress { degree: 2, steps_folded: 4 },
+            ]
+        );
+        crate::proof::verify(&verifier_params, &prover.finalize().unwrap()).unwrap();
+    }
 }
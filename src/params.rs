@@ -1,20 +1,75 @@
-use ark_pallas::{constraints::GVar, Fr, Projective};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, PrimeField};
+use ark_pallas::{constraints::GVar, Fq, Fr, Projective};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::rand::RngCore;
 use ark_vesta::{constraints::GVar as GVar2, Projective as Projective2};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use crate::errors::GrapevineError;
+use crate::utils::{
+    hash::circomlib_poseidon_hash,
+    inputs::{bigint_to_f, serialize_phrase},
+};
+#[cfg(feature = "prover")]
+use crate::utils::wrapper::CircomWrapper;
 
 use sonobe::{
-    commitment::{pedersen::Pedersen, CommitmentScheme},
+    ccs::r1cs::R1CS,
+    commitment::{
+        pedersen::{Params as PedersenParams, Pedersen},
+        CommitmentScheme,
+    },
     folding::nova::{get_r1cs, ProverParams, VerifierParams},
     frontend::FCircuit,
-    transcript::poseidon::poseidon_test_config
+    transcript::poseidon::poseidon_test_config,
+    utils::vec::SparseMatrix,
 };
 
-pub fn test_nova_setup<FC: FCircuit<Fr>>(
-    f_circuit: FC
-) -> (
-    ProverParams<Projective, Projective2, Pedersen<Projective>, Pedersen<Projective2>>,
-    VerifierParams<Projective, Projective2>
-) {
-    let mut rng = ark_std::test_rng();
+pub type GrapevineProverParams =
+    ProverParams<Projective, Projective2, Pedersen<Projective>, Pedersen<Projective2>>;
+pub type GrapevineVerifierParams = VerifierParams<Projective, Projective2>;
+
+// A folding backend (curve cycle and commitment scheme) compiled into this build
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackendInfo {
+    pub name: &'static str,
+    pub curve: &'static str,
+    pub commitment_scheme: &'static str,
+    pub requires_trusted_setup: bool,
+}
+
+/**
+ * Lists the folding backends this build can prove with
+ * @dev only nova over pallas/vesta with pedersen commitments is implemented, which is
+ *      transparent. Feature gated backends are added here as they are implemented
+ *
+ * @return - the compiled in backends
+ */
+pub fn supported_backends() -> Vec<BackendInfo> {
+    vec![BackendInfo {
+        name: "pallas-pedersen",
+        curve: "pallas/vesta",
+        commitment_scheme: "pedersen",
+        requires_trusted_setup: false,
+    }]
+}
+
+/**
+ * Generates the nova prover and verifier params for a function circuit
+ * @dev the circuit may be backed by artifacts on disk or in memory
+ *
+ * @param f_circuit - the function circuit to generate params for
+ * @param rng - the randomness source for the pedersen params
+ * @return - the prover and verifier params
+ */
+pub fn nova_setup<FC: FCircuit<Fr>, R: RngCore>(
+    f_circuit: FC,
+    rng: &mut R,
+) -> (GrapevineProverParams, GrapevineVerifierParams) {
     let poseidon_config = poseidon_test_config::<Fr>();
 
     // get CM & CF_CM len
@@ -25,16 +80,16 @@ pub fn test_nova_setup<FC: FCircuit<Fr>>(
     let cf_len = r1cs.A.n_rows;
     let cf_cf_len = cf_r1cs.A.n_rows;
 
-    let (pedersen_params, _) = Pedersen::<Projective>::setup(&mut rng, cf_len).unwrap();
-    let (cf_pedersen_params, _) = Pedersen::<Projective2>::setup(&mut rng, cf_cf_len).unwrap();
+    let (pedersen_params, _) = Pedersen::<Projective>::setup(&mut *rng, cf_len).unwrap();
+    let (cf_pedersen_params, _) = Pedersen::<Projective2>::setup(&mut *rng, cf_cf_len).unwrap();
 
-    let prover_params = 
+    let prover_params =
         ProverParams::<Projective, Projective2, Pedersen<Projective>, Pedersen<Projective2>>{
            poseidon_config: poseidon_config.clone(),
            cs_params: pedersen_params,
            cf_cs_params: cf_pedersen_params
         };
-    
+
     let verifier_params = VerifierParams::<Projective, Projective2>{
         poseidon_config: poseidon_config.clone(),
         r1cs,
@@ -42,4 +97,631 @@ pub fn test_nova_setup<FC: FCircuit<Fr>>(
     };
 
     (prover_params, verifier_params)
-}
\ No newline at end of file
+}
+
+pub fn test_nova_setup<FC: FCircuit<Fr>>(
+    f_circuit: FC
+) -> (GrapevineProverParams, GrapevineVerifierParams) {
+    let mut rng = ark_std::test_rng();
+    nova_setup(f_circuit, &mut rng)
+}
+
+// Row counts of the augmented function circuit and the cyclefold circuit it is paired with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CyclefoldStats {
+    pub native_rows: usize,
+    pub cf_rows: usize,
+}
+
+/**
+ * Reports the size of the augmented and cyclefold R1CS, i.e. how much the curve cycle adds
+ * @dev these are also the number of pedersen generators each set of params needs
+ *
+ * @param f_circuit - the function circuit to size
+ * @return - the row counts of both R1CS
+ */
+pub fn cyclefold_stats<FC: FCircuit<Fr>>(f_circuit: FC) -> Result<CyclefoldStats, GrapevineError> {
+    let (r1cs, cf_r1cs) = get_r1cs::<Projective, GVar, Projective2, GVar2, FC>(
+        &poseidon_test_config::<Fr>(),
+        f_circuit,
+    )
+    .map_err(|e| GrapevineError::SynthesisFailed(e.to_string()))?;
+    Ok(CyclefoldStats {
+        native_rows: r1cs.A.n_rows,
+        cf_rows: cf_r1cs.A.n_rows,
+    })
+}
+
+// Assembles prover params from externally provided (e.g. ceremony generated) pedersen params
+pub struct ProverParamsBuilder<FC: FCircuit<Fr>> {
+    f_circuit: FC,
+    cs_params: Option<PedersenParams<Projective>>,
+    cf_cs_params: Option<PedersenParams<Projective2>>,
+}
+
+impl<FC: FCircuit<Fr>> ProverParamsBuilder<FC> {
+    pub fn new(f_circuit: FC) -> Self {
+        Self {
+            f_circuit,
+            cs_params: None,
+            cf_cs_params: None,
+        }
+    }
+
+    // sets the pedersen params committing to the augmented circuit witness
+    pub fn cs_params(mut self, cs_params: PedersenParams<Projective>) -> Self {
+        self.cs_params = Some(cs_params);
+        self
+    }
+
+    // sets the pedersen params committing to the cyclefold circuit witness
+    pub fn cf_cs_params(mut self, cf_cs_params: PedersenParams<Projective2>) -> Self {
+        self.cf_cs_params = Some(cf_cs_params);
+        self
+    }
+
+    /**
+     * Assembles the prover and verifier params
+     * @dev each set of pedersen params must have one generator per row of its R1CS
+     *
+     * @return - the prover and verifier params
+     */
+    pub fn build(self) -> Result<(GrapevineProverParams, GrapevineVerifierParams), GrapevineError> {
+        let cs_params = self.cs_params.ok_or(GrapevineError::InvalidInput(
+            "Missing pedersen params".to_string(),
+        ))?;
+        let cf_cs_params = self.cf_cs_params.ok_or(GrapevineError::InvalidInput(
+            "Missing cyclefold pedersen params".to_string(),
+        ))?;
+
+        let poseidon_config = poseidon_test_config::<Fr>();
+        let (r1cs, cf_r1cs) = get_r1cs::<Projective, GVar, Projective2, GVar2, FC>(
+            &poseidon_config,
+            self.f_circuit,
+        )
+        .map_err(|e| GrapevineError::SynthesisFailed(e.to_string()))?;
+
+        // the supplied params must match the circuit they will commit to
+        if cs_params.generators.len() != r1cs.A.n_rows {
+            return Err(GrapevineError::ParamsLengthMismatch {
+                expected: r1cs.A.n_rows,
+                got: cs_params.generators.len(),
+            });
+        }
+        if cf_cs_params.generators.len() != cf_r1cs.A.n_rows {
+            return Err(GrapevineError::ParamsLengthMismatch {
+                expected: cf_r1cs.A.n_rows,
+                got: cf_cs_params.generators.len(),
+            });
+        }
+
+        let prover_params = GrapevineProverParams {
+            poseidon_config: poseidon_config.clone(),
+            cs_params,
+            cf_cs_params,
+        };
+        let verifier_params = GrapevineVerifierParams {
+            poseidon_config,
+            r1cs,
+            cf_r1cs,
+        };
+        Ok((prover_params, verifier_params))
+    }
+}
+
+// Encoding used when persisting prover params
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamsFormat {
+    // this crate's encoding: both pedersen params, compressed
+    Native,
+}
+
+/**
+ * Saves prover params to a writer
+ * @dev the poseidon config is not written as it is always `poseidon_test_config`
+ *
+ * @param prover_params - the params to save
+ * @param writer - the destination of the serialized params
+ * @param format - the encoding to write the params in
+ */
+pub fn save_params<W: Write>(
+    prover_params: &GrapevineProverParams,
+    mut writer: W,
+    format: ParamsFormat,
+) -> Result<(), GrapevineError> {
+    match format {
+        ParamsFormat::Native => (
+            &prover_params.cs_params.h,
+            &prover_params.cs_params.generators,
+            &prover_params.cf_cs_params.h,
+            &prover_params.cf_cs_params.generators,
+        )
+            .serialize_compressed(&mut writer)
+            .map_err(|e| GrapevineError::SerializationError(e.to_string())),
+    }
+}
+
+/**
+ * Loads prover params from a reader
+ * @dev verifier params are derived from the circuit, e.g. with `ProverParamsBuilder`
+ *
+ * @param reader - the source of the serialized params
+ * @param format - the encoding the params were saved in
+ * @return - the prover params
+ */
+pub fn load_params<R: Read>(
+    mut reader: R,
+    format: ParamsFormat,
+) -> Result<GrapevineProverParams, GrapevineError> {
+    match format {
+        ParamsFormat::Native => {
+            let (h, generators, cf_h, cf_generators) = <(
+                Projective,
+                Vec<<Projective as CurveGroup>::Affine>,
+                Projective2,
+                Vec<<Projective2 as CurveGroup>::Affine>,
+            )>::deserialize_compressed(&mut reader)
+            .map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+            Ok(GrapevineProverParams {
+                poseidon_config: poseidon_test_config::<Fr>(),
+                cs_params: PedersenParams { h, generators },
+                cf_cs_params: PedersenParams {
+                    h: cf_h,
+                    generators: cf_generators,
+                },
+            })
+        }
+    }
+}
+
+/**
+ * Saves verifier params to a writer
+ * @dev writes both R1CS, which verifier-only builds cannot derive without the circom witness
+ *      machinery. The poseidon config is not written as it is always `poseidon_test_config`
+ *
+ * @param verifier_params - the params to save
+ * @param writer - the destination of the serialized params
+ */
+pub fn save_verifier_params<W: Write>(
+    verifier_params: &GrapevineVerifierParams,
+    mut writer: W,
+) -> Result<(), GrapevineError> {
+    serialize_r1cs(&verifier_params.r1cs, &mut writer)
+        .and_then(|_| serialize_r1cs(&verifier_params.cf_r1cs, &mut writer))
+        .map_err(|e| GrapevineError::SerializationError(e.to_string()))
+}
+
+/**
+ * Loads verifier params written by `save_verifier_params`
+ * @dev builds without the `prover` feature, so a verifier never needs the circom artifacts
+ *
+ * @param reader - the source of the serialized params
+ * @return - the verifier params
+ */
+pub fn load_verifier_params<R: Read>(
+    mut reader: R,
+) -> Result<GrapevineVerifierParams, GrapevineError> {
+    let r1cs = deserialize_r1cs::<Fr, _>(&mut reader)
+        .map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+    let cf_r1cs = deserialize_r1cs::<Fq, _>(&mut reader)
+        .map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+    Ok(GrapevineVerifierParams {
+        poseidon_config: poseidon_test_config::<Fr>(),
+        r1cs,
+        cf_r1cs,
+    })
+}
+
+// writes an R1CS: its public input count then each matrix's dimensions and (value, column) rows
+fn serialize_r1cs<F: PrimeField, W: Write>(
+    r1cs: &R1CS<F>,
+    mut writer: W,
+) -> Result<(), SerializationError> {
+    (r1cs.l as u64).serialize_compressed(&mut writer)?;
+    for matrix in [&r1cs.A, &r1cs.B, &r1cs.C] {
+        let coeffs = matrix
+            .coeffs
+            .iter()
+            .map(|row| row.iter().map(|(value, col)| (*value, *col as u64)).collect())
+            .collect::<Vec<Vec<(F, u64)>>>();
+        (matrix.n_rows as u64, matrix.n_cols as u64, coeffs).serialize_compressed(&mut writer)?;
+    }
+    Ok(())
+}
+
+// reads an R1CS written by `serialize_r1cs`
+fn deserialize_r1cs<F: PrimeField, R: Read>(mut reader: R) -> Result<R1CS<F>, SerializationError> {
+    let l = u64::deserialize_compressed(&mut reader)?;
+    let mut read_matrix = || -> Result<SparseMatrix<F>, SerializationError> {
+        let (n_rows, n_cols, coeffs) =
+            <(u64, u64, Vec<Vec<(F, u64)>>)>::deserialize_compressed(&mut reader)?;
+        if coeffs.len() as u64 != n_rows
+            || coeffs.iter().flatten().any(|(_, col)| *col >= n_cols)
+        {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(SparseMatrix {
+            n_rows: n_rows as usize,
+            n_cols: n_cols as usize,
+            coeffs: coeffs
+                .into_iter()
+                .map(|row| row.into_iter().map(|(value, col)| (value, col as usize)).collect())
+                .collect(),
+        })
+    };
+    let (a, b, c) = (read_matrix()?, read_matrix()?, read_matrix()?);
+    Ok(R1CS {
+        l: l as usize,
+        A: a,
+        B: b,
+        C: c,
+    })
+}
+
+// Prover params stamped with the fingerprint of the circom artifacts they were generated for
+#[cfg(feature = "prover")]
+pub struct FingerprintedProverParams {
+    pub prover_params: GrapevineProverParams,
+    pub circuit_fingerprint: String,
+}
+
+#[cfg(feature = "prover")]
+impl FingerprintedProverParams {
+    // stamps params with the fingerprint of the artifacts they were just generated from
+    pub fn new(
+        prover_params: GrapevineProverParams,
+        wrapper: &CircomWrapper<Fr>,
+    ) -> Result<Self, GrapevineError> {
+        Ok(Self {
+            prover_params,
+            circuit_fingerprint: wrapper.circuit_fingerprint()?,
+        })
+    }
+
+    // saves the fingerprint followed by the params in the native format
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), GrapevineError> {
+        self.circuit_fingerprint
+            .serialize_compressed(&mut writer)
+            .map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+        save_params(&self.prover_params, writer, ParamsFormat::Native)
+    }
+
+    // loads params saved with `save`
+    pub fn load<R: Read>(mut reader: R) -> Result<Self, GrapevineError> {
+        let circuit_fingerprint = String::deserialize_compressed(&mut reader)
+            .map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+        Ok(Self {
+            prover_params: load_params(reader, ParamsFormat::Native)?,
+            circuit_fingerprint,
+        })
+    }
+}
+
+/**
+ * Checks that params were generated for the circom artifacts currently on hand
+ * @dev catches artifacts recompiled without regenerating params, which otherwise only surfaces
+ *      as proofs that fail to verify
+ *
+ * @param prover_params - the params stamped with the fingerprint at generation time
+ * @param wrapper - the circom wrapper over the current artifacts
+ * @return - ok if the fingerprints match, StaleParams otherwise
+ */
+#[cfg(feature = "prover")]
+pub fn check_params_match_artifacts(
+    prover_params: &FingerprintedProverParams,
+    wrapper: &CircomWrapper<Fr>,
+) -> Result<(), GrapevineError> {
+    let artifacts = wrapper.circuit_fingerprint()?;
+    if artifacts != prover_params.circuit_fingerprint {
+        return Err(GrapevineError::StaleParams {
+            params: prover_params.circuit_fingerprint.clone(),
+            artifacts,
+        });
+    }
+    Ok(())
+}
+
+/**
+ * Computes a digest of the verifier params (both R1CS shapes and coefficients)
+ *
+ * @param verifier_params - the params to digest
+ * @return - the sha256 digest of the params
+ */
+pub fn params_digest(verifier_params: &GrapevineVerifierParams) -> [u8; 32] {
+    r1cs_digest(&verifier_params.r1cs, &verifier_params.cf_r1cs)
+}
+
+/**
+ * Computes a digest of a Poseidon config (round counts, sbox, round constants and mds matrix)
+ *
+ * @param config - the poseidon config the folding transcript is instantiated with
+ * @return - the sha256 digest of the config
+ */
+pub fn poseidon_config_digest(config: &PoseidonConfig<Fr>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for value in [config.full_rounds, config.partial_rounds, config.rate, config.capacity] {
+        hasher.update((value as u64).to_be_bytes());
+    }
+    hasher.update(config.alpha.to_be_bytes());
+    for matrix in [&config.ark, &config.mds] {
+        hasher.update((matrix.len() as u64).to_be_bytes());
+        for row in matrix.iter() {
+            hasher.update((row.len() as u64).to_be_bytes());
+            for value in row.iter() {
+                hasher.update(value.into_bigint().to_bytes_be());
+            }
+        }
+    }
+    hasher.finalize().into()
+}
+
+// Phrase hashed to compare poseidon configs against circomlib
+const POSEIDON_TEST_PHRASE: &str = "grapevine poseidon test vector";
+
+// circomlib's Poseidon(6) of the serialized test phrase, over the bn128 prime the circuit is
+// compiled with (see circom/compile.sh)
+const POSEIDON_TEST_VECTOR: &str =
+    "7623139467532704205092605315724539211339481960115167685417238877504512359665";
+
+/**
+ * Checks a poseidon config hashes like circomlib, whose round constants and mds matrix are
+ * compiled into the circuit
+ * @dev hashes the untagged chunks of a known phrase like the circuit's phrase hasher and
+ *      compares them to circomlib's hash of the same chunks. A mismatch means hashes computed
+ *      under the config diverge from the circuit's
+ *
+ * @param config - the width 7 poseidon config to check
+ * @return - ok if both agree, PoseidonMismatch otherwise
+ */
+pub fn verify_poseidon_compatibility<F: PrimeField>(
+    config: &PoseidonConfig<F>,
+) -> Result<(), GrapevineError> {
+    let chunks = serialize_phrase(&String::from(POSEIDON_TEST_PHRASE))
+        .map_err(|e| GrapevineError::InvalidInput(e.to_string()))?
+        .iter()
+        .map(bigint_to_f::<F>)
+        .collect::<Result<Vec<F>, GrapevineError>>()?;
+    let expected = F::from_str(POSEIDON_TEST_VECTOR)
+        .map_err(|_| GrapevineError::FieldConversion(POSEIDON_TEST_VECTOR.to_string()))?;
+    if circomlib_poseidon_hash(config, &chunks)? != expected {
+        return Err(GrapevineError::PoseidonMismatch);
+    }
+    Ok(())
+}
+
+/**
+ * Computes the digest of the augmented and cyclefold R1CS the params are generated for
+ *
+ * @param r1cs - the augmented function circuit R1CS
+ * @param cf_r1cs - the cyclefold circuit R1CS
+ * @return - the sha256 digest of both R1CS
+ */
+pub fn r1cs_digest(r1cs: &R1CS<Fr>, cf_r1cs: &R1CS<Fq>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hash_r1cs(&mut hasher, r1cs);
+    hash_r1cs(&mut hasher, cf_r1cs);
+    hasher.finalize().into()
+}
+
+// absorbs the dimensions and coefficients of an R1CS into a hasher
+fn hash_r1cs<F: PrimeField>(hasher: &mut Sha256, r1cs: &R1CS<F>) {
+    hasher.update((r1cs.l as u64).to_be_bytes());
+    for matrix in [&r1cs.A, &r1cs.B, &r1cs.C] {
+        hasher.update((matrix.n_rows as u64).to_be_bytes());
+        hasher.update((matrix.n_cols as u64).to_be_bytes());
+        for row in matrix.coeffs.iter() {
+            hasher.update((row.len() as u64).to_be_bytes());
+            for (value, col) in row.iter() {
+                let mut bytes = Vec::new();
+                value.serialize_compressed(&mut bytes).unwrap();
+                hasher.update(&bytes);
+                hasher.update((*col as u64).to_be_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::GrapevineFCircuit;
+    use crate::test_utils::{sample_circuit, MockFCircuit};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_params_from_bytes() {
+        let r1cs_path = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+        let wasm_path = PathBuf::from("./circom/artifacts/grapevine.wasm");
+        let r1cs_bytes = std::fs::read(&r1cs_path).unwrap();
+        let wasm_bytes = std::fs::read(&wasm_path).unwrap();
+
+        // generate params from file and byte backed circuits with the same rng
+        let file_circuit = GrapevineFCircuit::<Fr>::new((r1cs_path, wasm_path));
+        let (file_pp, file_vp) = test_nova_setup::<GrapevineFCircuit<Fr>>(file_circuit);
+        let bytes_circuit = GrapevineFCircuit::<Fr>::from_bytes(r1cs_bytes, wasm_bytes);
+        let (bytes_pp, bytes_vp) = test_nova_setup::<GrapevineFCircuit<Fr>>(bytes_circuit);
+
+        assert_eq!(params_digest(&file_vp), params_digest(&bytes_vp));
+        assert_eq!(file_pp.cs_params.generators, bytes_pp.cs_params.generators);
+    }
+
+    #[test]
+    fn test_supported_backends() {
+        let backends = supported_backends();
+        let pallas = backends
+            .iter()
+            .find(|backend| backend.name == "pallas-pedersen")
+            .unwrap();
+        assert_eq!(pallas.commitment_scheme, "pedersen");
+        assert!(!pallas.requires_trusted_setup);
+    }
+
+    #[test]
+    fn test_cyclefold_stats() {
+        let f_circuit = sample_circuit();
+        let stats = cyclefold_stats(f_circuit.clone()).unwrap();
+        let (r1cs, cf_r1cs) = get_r1cs::<Projective, GVar, Projective2, GVar2, _>(
+            &poseidon_test_config::<Fr>(),
+            f_circuit,
+        )
+        .unwrap();
+        assert_eq!(stats.native_rows, r1cs.A.n_rows);
+        assert_eq!(stats.cf_rows, cf_r1cs.A.n_rows);
+        assert!(stats.native_rows > 0 && stats.cf_rows > 0);
+    }
+
+    // circomlib's poseidon config for a width over bn128, read from the vendored constants
+    fn circomlib_poseidon_config(width: usize) -> PoseidonConfig<ark_bn254::Fr> {
+        let constants = std::fs::read_to_string(
+            "./circom/node_modules/circomlib/circuits/poseidon_constants_old.circom",
+        )
+        .unwrap();
+        // the array `function <name>(t)` returns for t == width
+        let values = |name: &str| -> Vec<ark_bn254::Fr> {
+            let start = constants.find(&format!("function {}(t)", name)).unwrap();
+            let function = &constants[start..];
+            let branch = &function[function.find(&format!("t == {}", width)).unwrap()..];
+            branch[branch.find("return").unwrap()..branch.find(';').unwrap()]
+                .split(|c: char| !c.is_ascii_digit())
+                .filter(|value| !value.is_empty())
+                .map(|value| ark_bn254::Fr::from_str(value).unwrap())
+                .collect()
+        };
+        let rows = |values: Vec<ark_bn254::Fr>| {
+            values.chunks(width).map(|row| row.to_vec()).collect()
+        };
+        // partial rounds by width, as in circomlib's poseidon.circom
+        let partial_rounds = [56, 57, 56, 60, 60, 63, 64, 63][width - 2];
+        PoseidonConfig::new(
+            8,
+            partial_rounds,
+            5,
+            rows(values("POSEIDON_M")),
+            rows(values("POSEIDON_C")),
+            width - 1,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_verify_poseidon_compatibility() {
+        // circomlib's own Poseidon(2) vector, see circomlib/test/poseidoncircuit.js
+        let inputs = [ark_bn254::Fr::from(1), ark_bn254::Fr::from(2)];
+        let expected = ark_bn254::Fr::from_str(
+            "7853200120776062878684798364095072458815029376092732009249414926327459813530",
+        )
+        .unwrap();
+        let config = circomlib_poseidon_config(3);
+        assert_eq!(circomlib_poseidon_hash(&config, &inputs).unwrap(), expected);
+
+        verify_poseidon_compatibility(&circomlib_poseidon_config(7)).unwrap();
+    }
+
+    #[test]
+    fn test_verify_poseidon_compatibility_wrong_config() {
+        // a single round constant off is caught
+        let mut config = circomlib_poseidon_config(7);
+        config.ark[0][0] += ark_bn254::Fr::from(1);
+        assert!(matches!(
+            verify_poseidon_compatibility(&config),
+            Err(GrapevineError::PoseidonMismatch)
+        ));
+
+        // as is the width 3 config native hashes are computed with
+        assert!(verify_poseidon_compatibility(&poseidon_test_config::<Fr>()).is_err());
+    }
+
+    #[test]
+    fn test_prover_params_builder() {
+        let f_circuit = sample_circuit();
+        let (_, verifier_params) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let cf_len = verifier_params.r1cs.A.n_rows;
+        let cf_cf_len = verifier_params.cf_r1cs.A.n_rows;
+
+        // params supplied from an external setup of the right size are accepted
+        let mut rng = ark_std::test_rng();
+        let (cs_params, _) = Pedersen::<Projective>::setup(&mut rng, cf_len).unwrap();
+        let (cf_cs_params, _) = Pedersen::<Projective2>::setup(&mut rng, cf_cf_len).unwrap();
+        let (prover_params, built_verifier_params) = ProverParamsBuilder::new(f_circuit.clone())
+            .cs_params(cs_params.clone())
+            .cf_cs_params(cf_cs_params.clone())
+            .build()
+            .unwrap();
+        assert_eq!(prover_params.cs_params.generators, cs_params.generators);
+        assert_eq!(params_digest(&built_verifier_params), params_digest(&verifier_params));
+
+        // undersized params are rejected
+        let (short_params, _) = Pedersen::<Projective>::setup(&mut rng, cf_len - 1).unwrap();
+        let res = ProverParamsBuilder::new(f_circuit)
+            .cs_params(short_params)
+            .cf_cs_params(cf_cs_params)
+            .build();
+        assert!(matches!(
+            res,
+            Err(GrapevineError::ParamsLengthMismatch { expected, got })
+                if expected == cf_len && got == cf_len - 1
+        ));
+    }
+
+    #[test]
+    fn test_save_and_load_params() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit);
+
+        // native params round trip
+        let mut bytes = Vec::new();
+        save_params(&prover_params, &mut bytes, ParamsFormat::Native).unwrap();
+        let loaded = load_params(bytes.as_slice(), ParamsFormat::Native).unwrap();
+        assert_eq!(loaded.cs_params.h, prover_params.cs_params.h);
+        assert_eq!(loaded.cs_params.generators, prover_params.cs_params.generators);
+        assert_eq!(loaded.cf_cs_params.generators, prover_params.cf_cs_params.generators);
+    }
+
+    #[test]
+    fn test_save_and_load_verifier_params() {
+        // the mock circuit needs no circom artifacts, so this runs without the prover feature
+        let (_, verifier_params) = test_nova_setup(MockFCircuit::new(()));
+        let mut bytes = Vec::new();
+        save_verifier_params(&verifier_params, &mut bytes).unwrap();
+        let loaded = load_verifier_params(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.r1cs, verifier_params.r1cs);
+        assert_eq!(loaded.cf_r1cs, verifier_params.cf_r1cs);
+        assert_eq!(params_digest(&loaded), params_digest(&verifier_params));
+
+        // truncated params are rejected
+        assert!(matches!(
+            load_verifier_params(&bytes[..bytes.len() - 1]),
+            Err(GrapevineError::SerializationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_params_match_artifacts() {
+        let wrapper = CircomWrapper::<Fr>::new(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        );
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(sample_circuit());
+        let params = FingerprintedProverParams::new(prover_params, &wrapper).unwrap();
+        let fingerprint = params.circuit_fingerprint.clone();
+        check_params_match_artifacts(&params, &wrapper).unwrap();
+
+        // the fingerprint survives a save and load
+        let mut bytes = Vec::new();
+        params.save(&mut bytes).unwrap();
+        let loaded = FingerprintedProverParams::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.circuit_fingerprint, fingerprint);
+        check_params_match_artifacts(&loaded, &wrapper).unwrap();
+
+        // params stamped for a different circuit are stale
+        let stale = FingerprintedProverParams {
+            circuit_fingerprint: String::from("00000000"),
+            ..loaded
+        };
+        assert!(matches!(
+            check_params_match_artifacts(&stale, &wrapper),
+            Err(GrapevineError::StaleParams { params, artifacts })
+                if params == "00000000" && artifacts == fingerprint
+        ));
+    }
+}
@@ -0,0 +1,1328 @@
+use ark_ff::{BigInteger, PrimeField};
+use ark_pallas::{Fr, Projective};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_vesta::Projective as Projective2;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use num_bigint::BigInt;
+use sonobe::{frontend::FCircuit, FoldingScheme};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use crate::{
+    circom::GrapevineFCircuit,
+    errors::GrapevineError,
+    nova::{replay_native, NovaFor, NOVA},
+    params::{params_digest, poseidon_config_digest, GrapevineVerifierParams},
+    utils::{
+        hash::{identity_commitment, is_identity_in_chain},
+        inputs::{is_fresh_start, step_count_to_fr},
+        wrapper::{CircomPrivateInput, CircomWrapper},
+        DEGREE_INDEX, PHRASE_HASH_INDEX, SECRET_HASH_INDEX,
+    },
+};
+
+type NovaRunningInstance =
+    <NOVA as FoldingScheme<Projective, Projective2, GrapevineFCircuit<Fr>>>::RunningInstance;
+type NovaIncomingInstance =
+    <NOVA as FoldingScheme<Projective, Projective2, GrapevineFCircuit<Fr>>>::IncomingInstance;
+type NovaCyclefoldInstance =
+    <NOVA as FoldingScheme<Projective, Projective2, GrapevineFCircuit<Fr>>>::CFInstance;
+
+// Version of the serialized proof format written by this build
+// (2: adds the optional embedded poseidon config digest)
+pub const PROOF_FORMAT_VERSION: u8 = 2;
+
+// Serialized proof format versions this build can read
+pub const SUPPORTED_VERSIONS: RangeInclusive<u8> = 1..=PROOF_FORMAT_VERSION;
+
+// The folded (running) instance accumulating all prior steps
+#[derive(Clone, Debug)]
+pub struct RunningInstance(pub NovaRunningInstance);
+
+// The instance of the last folded step
+#[derive(Clone, Debug)]
+pub struct IncomingInstance(pub NovaIncomingInstance);
+
+// The cyclefold instance on the secondary curve
+#[derive(Clone, Debug)]
+pub struct CyclefoldInstance(pub NovaCyclefoldInstance);
+
+// The three instances required to verify a fold, named so they cannot be passed out of order
+#[derive(Clone, Debug)]
+pub struct GrapevineInstances {
+    pub running: RunningInstance,
+    pub incoming: IncomingInstance,
+    pub cyclefold: CyclefoldInstance,
+}
+
+impl<FC: FCircuit<Fr>> From<&NovaFor<FC>> for GrapevineInstances {
+    fn from(folding_scheme: &NovaFor<FC>) -> Self {
+        let (running, incoming, cyclefold) = folding_scheme.instances();
+        Self {
+            running: RunningInstance(running),
+            incoming: IncomingInstance(incoming),
+            cyclefold: CyclefoldInstance(cyclefold),
+        }
+    }
+}
+
+impl GrapevineInstances {
+    /**
+     * Verifies the instances against the claimed ivc states
+     *
+     * @param verifier_params - the nova verifier params
+     * @param z_0 - the starting ivc state
+     * @param z_i - the final ivc state
+     * @param num_steps - the number of steps folded
+     */
+    pub fn verify(
+        &self,
+        verifier_params: &GrapevineVerifierParams,
+        z_0: Vec<Fr>,
+        z_i: Vec<Fr>,
+        num_steps: Fr,
+    ) -> Result<(), GrapevineError> {
+        NOVA::verify(
+            verifier_params.clone(),
+            z_0,
+            z_i,
+            num_steps,
+            self.running.0.clone(),
+            self.incoming.0.clone(),
+            self.cyclefold.0.clone(),
+        )
+        .map_err(|e| GrapevineError::VerificationFailed(e.to_string()))
+    }
+}
+
+// A folded grapevine chain: the public ivc states and the instances needed to verify them
+#[derive(Clone, Debug)]
+pub struct GrapevineProof {
+    pub z_0: Vec<Fr>,
+    pub z_i: Vec<Fr>,
+    pub num_steps: usize,
+    pub params_digest: [u8; 32],
+    // digest of the poseidon config, embedded to make the proof self describing (see
+    // `with_embedded_params`)
+    pub poseidon_digest: Option<[u8; 32]>,
+    pub instances: GrapevineInstances,
+}
+
+impl GrapevineProof {
+    // returns the degree of separation proven in the final state
+    pub fn degree(&self) -> Result<u64, GrapevineError> {
+        fr_to_u64(self.z_i[DEGREE_INDEX])
+    }
+
+    // returns the phrase hash carried in the final state
+    pub fn phrase_hash(&self) -> Fr {
+        self.z_i[PHRASE_HASH_INDEX]
+    }
+
+    // returns the degree secret hash at the head of the chain
+    pub fn root(&self) -> Fr {
+        self.z_i[SECRET_HASH_INDEX]
+    }
+
+    /**
+     * Describes the proof in one line for logs and the CLI
+     *
+     * @return - e.g. "Grapevine proof: degree 3, root 0x1a2b3c4d..., 6 steps, 4.2 KB"
+     */
+    pub fn summary(&self) -> String {
+        let degree = match self.degree() {
+            Ok(degree) => degree.to_string(),
+            Err(_) => "?".to_string(),
+        };
+        let root = self.root().into_bigint().to_bytes_be()[..4]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let size = match self.to_bytes() {
+            Ok(bytes) => format!("{:.1} KB", bytes.len() as f64 / 1024.0),
+            Err(_) => "? KB".to_string(),
+        };
+        format!(
+            "Grapevine proof: degree {}, root 0x{}..., {} steps, {}",
+            degree, root, self.num_steps, size
+        )
+    }
+
+    /**
+     * Determines whether two proofs assert the same claim
+     * @dev only compares the public states and step count, not the folded instances
+     *
+     * @param other - the proof to compare against
+     * @return - true if both proofs prove the same chain start, length and final state
+     */
+    pub fn same_claim(&self, other: &Self) -> bool {
+        self.z_0 == other.z_0 && self.z_i == other.z_i && self.num_steps == other.num_steps
+    }
+
+    /**
+     * Returns the public inputs a decider proof over this chain would expose
+     * @dev ordered as the step counter, the initial state, then the final state
+     *
+     * @return - [i, z_0..., z_i...]
+     */
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![Fr::from(self.num_steps as u64)];
+        inputs.extend_from_slice(&self.z_0);
+        inputs.extend_from_slice(&self.z_i);
+        inputs
+    }
+
+    // embeds the digest of the params' poseidon config so `verify_self_contained` can check it
+    pub fn with_embedded_params(mut self, verifier_params: &GrapevineVerifierParams) -> Self {
+        self.poseidon_digest = Some(poseidon_config_digest(&verifier_params.poseidon_config));
+        self
+    }
+
+    /**
+     * Checks the proof folds whole (logic, chaff) pairs
+     * @dev a legitimate chain always alternates logic and chaff steps so has an even step count
+     */
+    pub fn validate_step_parity(&self) -> Result<(), GrapevineError> {
+        if self.num_steps % 2 != 0 {
+            return Err(GrapevineError::OddStepCount(self.num_steps));
+        }
+        Ok(())
+    }
+
+    /**
+     * Serializes the proof into a writer
+     * @dev prefixed with PROOF_FORMAT_VERSION so older readers can reject newer formats
+     *
+     * @param writer - the destination of the serialized proof
+     */
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), GrapevineError> {
+        PROOF_FORMAT_VERSION
+            .serialize_compressed(&mut writer)
+            .and_then(|_| self.z_0.serialize_compressed(&mut writer))
+            .and_then(|_| self.z_i.serialize_compressed(&mut writer))
+            .and_then(|_| (self.num_steps as u64).serialize_compressed(&mut writer))
+            .and_then(|_| self.params_digest.serialize_compressed(&mut writer))
+            .and_then(|_| self.poseidon_digest.serialize_compressed(&mut writer))
+            .and_then(|_| self.instances.running.0.serialize_compressed(&mut writer))
+            .and_then(|_| self.instances.incoming.0.serialize_compressed(&mut writer))
+            .and_then(|_| self.instances.cyclefold.0.serialize_compressed(&mut writer))
+            .map_err(|e| GrapevineError::SerializationError(e.to_string()))
+    }
+
+    /**
+     * Deserializes a proof from a reader, consuming only the bytes of the proof
+     * @dev the format version is read first and anything outside SUPPORTED_VERSIONS is rejected
+     *      before the rest of the proof is parsed. Version 1 proofs carry no poseidon digest
+     *
+     * @param reader - the source of the serialized proof
+     * @return - the deserialized proof
+     */
+    pub fn deserialize<R: Read>(mut reader: R) -> Result<Self, GrapevineError> {
+        let map_err = |e: ark_serialize::SerializationError| {
+            GrapevineError::SerializationError(e.to_string())
+        };
+        let version = u8::deserialize_compressed(&mut reader).map_err(map_err)?;
+        if !SUPPORTED_VERSIONS.contains(&version) {
+            return Err(GrapevineError::UnsupportedVersion {
+                found: version,
+                supported: (*SUPPORTED_VERSIONS.start(), *SUPPORTED_VERSIONS.end()),
+            });
+        }
+        let z_0 = Vec::<Fr>::deserialize_compressed(&mut reader).map_err(map_err)?;
+        let z_i = Vec::<Fr>::deserialize_compressed(&mut reader).map_err(map_err)?;
+        let num_steps = u64::deserialize_compressed(&mut reader).map_err(map_err)? as usize;
+        let params_digest = <[u8; 32]>::deserialize_compressed(&mut reader).map_err(map_err)?;
+        let poseidon_digest = match version {
+            1 => None,
+            _ => Option::<[u8; 32]>::deserialize_compressed(&mut reader).map_err(map_err)?,
+        };
+        let running = NovaRunningInstance::deserialize_compressed(&mut reader).map_err(map_err)?;
+        let incoming =
+            NovaIncomingInstance::deserialize_compressed(&mut reader).map_err(map_err)?;
+        let cyclefold =
+            NovaCyclefoldInstance::deserialize_compressed(&mut reader).map_err(map_err)?;
+        Ok(Self {
+            z_0,
+            z_i,
+            num_steps,
+            params_digest,
+            poseidon_digest,
+            instances: GrapevineInstances {
+                running: RunningInstance(running),
+                incoming: IncomingInstance(incoming),
+                cyclefold: CyclefoldInstance(cyclefold),
+            },
+        })
+    }
+
+    // serializes the proof into a byte vector
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GrapevineError> {
+        let mut bytes = Vec::new();
+        self.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    // deserializes a proof from a byte slice
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GrapevineError> {
+        Self::deserialize(bytes)
+    }
+}
+
+/**
+ * Verifies a folded grapevine proof
+ *
+ * @param verifier_params - the nova verifier params
+ * @param proof - the proof to verify
+ */
+pub fn verify(
+    verifier_params: &GrapevineVerifierParams,
+    proof: &GrapevineProof,
+) -> Result<(), GrapevineError> {
+    check_proof_params_compatible(proof, verifier_params)?;
+    proof.validate_step_parity()?;
+    proof.instances.verify(
+        verifier_params,
+        proof.z_0.clone(),
+        proof.z_i.clone(),
+        step_count_to_fr(proof.num_steps)?,
+    )
+}
+
+// The meaningful public values a verified proof attests to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrapevineClaim {
+    pub root: Fr,
+    pub degree: u64,
+    pub phrase_hash: Fr,
+}
+
+// Domain separator of the message an attestation signs
+const ATTESTATION_DOMAIN: &[u8] = b"grapevine-attestation-v1";
+
+// JSON-LD context of the W3C verifiable credentials data model the exported credentials follow
+const VC_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+impl GrapevineClaim {
+    // encodes the claim as the domain separated message an attestation signs
+    fn attestation_message(&self) -> Vec<u8> {
+        let mut message = ATTESTATION_DOMAIN.to_vec();
+        message.extend(self.root.into_bigint().to_bytes_be());
+        message.extend(self.degree.to_be_bytes());
+        message.extend(self.phrase_hash.into_bigint().to_bytes_be());
+        message
+    }
+
+    /**
+     * Signs the claim so a verifier can vouch for a proof without resharing it
+     * @dev only the claim is signed: whoever relies on the attestation trusts the signer to
+     *      have verified the proof
+     *
+     * @param signing_key - the verifier's ed25519 signing key
+     * @return - the claim with the verifier's signature over it
+     */
+    pub fn attest(&self, signing_key: &SigningKey) -> Attestation {
+        Attestation {
+            claim: self.clone(),
+            signature: signing_key.sign(&self.attestation_message()),
+        }
+    }
+
+    /**
+     * Exports the claim and the proof it was extracted from as a W3C verifiable credential
+     * @dev follows the VC data model 2.0 with the claim as the credential subject (field
+     *      elements as decimal strings) and the serialized proof embedded as a multibase
+     *      base16 ('f' prefixed hex) proof value. The credential is not signed by the issuer:
+     *      a wallet trusts it by verifying the embedded proof, not the issuer
+     *
+     * @param issuer_did - the DID of the party issuing the credential
+     * @param proof - the proof the claim was extracted from
+     * @return - the credential as a JSON document
+     */
+    pub fn to_verifiable_credential(
+        &self,
+        issuer_did: &str,
+        proof: &GrapevineProof,
+    ) -> Result<serde_json::Value, GrapevineError> {
+        // a wallet checks the embedded proof, which only backs the claim from a fresh start
+        if !is_fresh_start(&proof.z_0) {
+            return Err(GrapevineError::NotFreshStart);
+        }
+        let to_decimal =
+            |value: Fr| CircomWrapper::<Fr>::ark_primefield_to_num_bigint(value).to_string();
+        let proof_value = proof
+            .to_bytes()?
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        Ok(serde_json::json!({
+            "@context": [VC_CONTEXT],
+            "type": ["VerifiableCredential", "GrapevineDegreeCredential"],
+            "issuer": issuer_did,
+            "credentialSubject": {
+                "degree": self.degree,
+                "root": to_decimal(self.root),
+                "phraseHash": to_decimal(self.phrase_hash),
+            },
+            "proof": {
+                "type": "GrapevineNovaProof",
+                "proofFormatVersion": PROOF_FORMAT_VERSION,
+                "proofValue": format!("f{}", proof_value),
+            },
+        }))
+    }
+}
+
+// A claim signed by the verifier that checked the proof it was extracted from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attestation {
+    pub claim: GrapevineClaim,
+    pub signature: Signature,
+}
+
+impl Attestation {
+    // checks the attestation was signed by the holder of the given key over exactly this claim
+    pub fn verify(&self, public_key: &VerifyingKey) -> Result<(), GrapevineError> {
+        public_key
+            .verify(&self.claim.attestation_message(), &self.signature)
+            .map_err(|e| GrapevineError::VerificationFailed(e.to_string()))
+    }
+}
+
+/**
+ * Verifies a proof and extracts everything it attests to
+ * @dev only proofs folded from the fresh all-zero state are accepted. A proof folded from any
+ *      other state verifies just as well but its final state carries whatever degree and phrase
+ *      hash the prover started from, which nothing in the fold checked
+ *
+ * @param verifier_params - the nova verifier params
+ * @param proof - the proof to verify
+ * @return - the root, degree and phrase hash of the final state if the proof verifies
+ */
+pub fn verify_and_extract(
+    verifier_params: &GrapevineVerifierParams,
+    proof: &GrapevineProof,
+) -> Result<GrapevineClaim, GrapevineError> {
+    if !is_fresh_start(&proof.z_0) {
+        return Err(GrapevineError::NotFreshStart);
+    }
+    verify(verifier_params, proof)?;
+    Ok(GrapevineClaim {
+        root: proof.root(),
+        degree: proof.degree()?,
+        phrase_hash: proof.phrase_hash(),
+    })
+}
+
+// Whether a verified proof reaches a degree threshold, without the exact degree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DegreeAttestation {
+    pub threshold: u64,
+    pub satisfied: bool,
+}
+
+/**
+ * Verifies a proof and attests whether its degree is at least a threshold
+ * @dev hook for a range proof on the degree slot. This simple version is not zero knowledge:
+ *      whoever runs it holds the full proof and so learns the exact degree from its final
+ *      state. Only the attestation it hands on hides the degree, so it suits a trusted
+ *      verifier relaying "degree >= N" to a third party. Hiding the degree from the verifier
+ *      itself requires wrapping the degree in a range proof
+ *
+ * @param verifier_params - the nova verifier params
+ * @param proof - the proof whose degree is checked
+ * @param threshold - the minimum degree
+ * @return - the threshold and whether the proof's degree reaches it
+ */
+pub fn prove_degree_at_least(
+    verifier_params: &GrapevineVerifierParams,
+    proof: &GrapevineProof,
+    threshold: u64,
+) -> Result<DegreeAttestation, GrapevineError> {
+    let claim = verify_and_extract(verifier_params, proof)?;
+    Ok(DegreeAttestation {
+        threshold,
+        satisfied: claim.degree >= threshold,
+    })
+}
+
+/**
+ * Checks a target identity is not within a proof's chain, given the chain's inputs
+ * @dev hook for a zero knowledge non-membership proof. This version is the verifier-with-witness
+ *      case: the inputs are revealed to whoever runs it, who checks they reproduce the proof's
+ *      final state and that none of their degrees introduce the target. It does not verify the
+ *      proof itself
+ *
+ * @param f_circuit - the grapevine function circuit
+ * @param proof - the proof of the chain
+ * @param inputs - the interleaved (logic, chaff) step inputs the proof was folded from
+ * @param target_identity - the identity commitment (see `identity_commitment`) to exclude
+ * @return - ok if the target is not in the chain, VerificationFailed otherwise
+ */
+pub fn prove_not_linked(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    proof: &GrapevineProof,
+    inputs: &[CircomPrivateInput],
+    target_identity: Fr,
+) -> Result<(), GrapevineError> {
+    if replay_native(f_circuit, proof.z_0.clone(), inputs)? != proof.z_i {
+        return Err(GrapevineError::VerificationFailed(
+            "inputs do not reproduce the proof's final state".to_string(),
+        ));
+    }
+    if is_identity_in_chain(inputs, target_identity)? {
+        return Err(GrapevineError::VerificationFailed(
+            "target identity is in the chain".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/**
+ * Verifies a proof and that the identities revealed for its path match published commitments
+ * @dev the prover reveals the usernames and auth secrets of the path to the verifier, who
+ *      recomputes each identity commitment under the proof's phrase hash. The commitments are
+ *      checked first so a mismatched path is rejected without the cost of verifying
+ *
+ * @param verifier_params - the nova verifier params
+ * @param proof - the proof of the chain
+ * @param path_usernames - the username of each degree in the path
+ * @param secrets - the auth secret of each degree in the path
+ * @param expected_commitments - the published identity commitment of each degree in the path
+ * @return - VerificationFailed naming the first identity that does not match its commitment
+ */
+pub fn verify_against_commitments(
+    verifier_params: &GrapevineVerifierParams,
+    proof: &GrapevineProof,
+    path_usernames: &[String],
+    secrets: &[BigInt],
+    expected_commitments: &[Fr],
+) -> Result<(), GrapevineError> {
+    if path_usernames.len() != secrets.len() || secrets.len() != expected_commitments.len() {
+        return Err(GrapevineError::InvalidInput(format!(
+            "Path has {} usernames, {} secrets and {} commitments",
+            path_usernames.len(),
+            secrets.len(),
+            expected_commitments.len()
+        )));
+    }
+    let phrase_hash = proof.phrase_hash();
+    for (i, ((username, secret), expected)) in path_usernames
+        .iter()
+        .zip(secrets)
+        .zip(expected_commitments)
+        .enumerate()
+    {
+        if identity_commitment(phrase_hash, username, secret)? != *expected {
+            return Err(GrapevineError::VerificationFailed(format!(
+                "identity {} does not match its published commitment",
+                i
+            )));
+        }
+    }
+    verify(verifier_params, proof)
+}
+
+/**
+ * Verifies a batch of proofs in parallel on a dedicated pool of threads
+ * @dev the pool is built per call so a batch never uses more than `num_threads` cores
+ *
+ * @param verifier_params - the nova verifier params
+ * @param proofs - the proofs to verify
+ * @param num_threads - the number of threads to verify on
+ * @return - the verification result of each proof, in order
+ */
+pub fn verify_batch_with_threads(
+    verifier_params: &GrapevineVerifierParams,
+    proofs: &[GrapevineProof],
+    num_threads: usize,
+) -> Result<Vec<Result<(), GrapevineError>>, GrapevineError> {
+    if num_threads == 0 {
+        return Err(GrapevineError::InvalidInput(
+            "Verification pool needs at least one thread".to_string(),
+        ));
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map_err(|e| GrapevineError::InvalidInput(e.to_string()))?;
+    Ok(pool.install(|| {
+        proofs
+            .par_iter()
+            .map(|proof| verify(verifier_params, proof))
+            .collect()
+    }))
+}
+
+/**
+ * Checks a proof was generated against the same R1CS as the verifier params
+ * @dev cheap diagnostic run before verification, which otherwise fails obscurely
+ *
+ * @param proof - the proof carrying the digest of the params it was folded with
+ * @param verifier_params - the params the proof will be verified against
+ */
+pub fn check_proof_params_compatible(
+    proof: &GrapevineProof,
+    verifier_params: &GrapevineVerifierParams,
+) -> Result<(), GrapevineError> {
+    if proof.params_digest != params_digest(verifier_params) {
+        return Err(GrapevineError::ParamsMismatch);
+    }
+    Ok(())
+}
+
+/**
+ * Verifies a self describing proof, first checking it embeds digests of exactly the local params
+ * @dev a proof that does not embed its params is rejected rather than verified blindly
+ *
+ * @param proof - the proof embedding digests of the params it was folded with
+ * @param local_params - the verifier params available locally
+ */
+pub fn verify_self_contained(
+    proof: &GrapevineProof,
+    local_params: &GrapevineVerifierParams,
+) -> Result<(), GrapevineError> {
+    let poseidon_digest = proof.poseidon_digest.ok_or(GrapevineError::InvalidInput(
+        "Proof does not embed a digest of its params".to_string(),
+    ))?;
+    if poseidon_digest != poseidon_config_digest(&local_params.poseidon_config) {
+        return Err(GrapevineError::ParamsMismatch);
+    }
+    verify(local_params, proof)
+}
+
+// Remembers proofs that have already verified so they are not verified again
+#[derive(Debug, Default)]
+pub struct VerificationCache {
+    verified: Mutex<HashSet<[u8; 32]>>,
+    hits: AtomicUsize,
+}
+
+impl VerificationCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // returns the number of verifications served from the cache
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    // returns the number of distinct proofs cached as verified
+    pub fn len(&self) -> usize {
+        self.verified.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/**
+ * Verifies a proof, skipping verification if the exact same proof has already verified
+ * @dev keyed on sha256(params digest || proof bytes) so a different proof of the same claim, or
+ *      the same proof under different params, is always verified in full
+ *
+ * @param cache - the cache of previously verified proofs
+ * @param proof - the proof to verify
+ * @param verifier_params - the nova verifier params
+ */
+pub fn verify_cached(
+    cache: &VerificationCache,
+    proof: &GrapevineProof,
+    verifier_params: &GrapevineVerifierParams,
+) -> Result<(), GrapevineError> {
+    let mut hasher = Sha256::new();
+    hasher.update(params_digest(verifier_params));
+    hasher.update(proof.to_bytes()?);
+    let key: [u8; 32] = hasher.finalize().into();
+
+    if cache.verified.lock().unwrap().contains(&key) {
+        cache.hits.fetch_add(1, Ordering::Relaxed);
+        return Ok(());
+    }
+    verify(verifier_params, proof)?;
+    cache.verified.lock().unwrap().insert(key);
+    Ok(())
+}
+
+/**
+ * Deserializes a proof incrementally from a reader and verifies it
+ * @dev avoids buffering the serialized proof in memory before parsing
+ *
+ * @param verifier_params - the nova verifier params
+ * @param reader - the source of the serialized proof
+ */
+pub fn verify_from_reader<R: Read>(
+    verifier_params: &GrapevineVerifierParams,
+    reader: R,
+) -> Result<(), GrapevineError> {
+    let proof = GrapevineProof::deserialize(reader)?;
+    verify(verifier_params, &proof)
+}
+
+/**
+ * Checks the proof's final state carries a claimed phrase hash
+ * @dev does not verify the proof itself
+ *
+ * @param proof - the proof to check
+ * @param claimed_phrase_hash - the published hash of the phrase
+ */
+pub fn verify_phrase_hash(
+    proof: &GrapevineProof,
+    claimed_phrase_hash: Fr,
+) -> Result<(), GrapevineError> {
+    if proof.phrase_hash() != claimed_phrase_hash {
+        return Err(GrapevineError::PhraseHashMismatch);
+    }
+    Ok(())
+}
+
+/**
+ * Checks a proof continues the chain proven by a prior proof
+ * @dev does not verify either proof, only that the extension starts where the prior ended
+ *
+ * @param prior - the proof of the chain being extended
+ * @param extension - the proof of the segment folded on top of the prior chain
+ */
+pub fn verify_extends(
+    prior: &GrapevineProof,
+    extension: &GrapevineProof,
+) -> Result<(), GrapevineError> {
+    if extension.z_0 != prior.z_i {
+        return Err(GrapevineError::ChainDiscontinuity(
+            "extension does not start from the prior final state".to_string(),
+        ));
+    }
+    if extension.degree()? < prior.degree()? {
+        return Err(GrapevineError::ChainDiscontinuity(
+            "extension ends below the prior degree".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Largest proof frame read from a stream. A proof holds the ivc states and the folded instances,
+// none of which grow with the chain, so real proofs are a small fraction of this
+pub const MAX_PROOF_FRAME_LEN: usize = 64 * 1024;
+
+/**
+ * Writes a proof to a stream prefixed with its 4-byte big-endian length
+ *
+ * @param w - the stream to write the frame to
+ * @param proof - the proof to frame
+ */
+pub fn write_proof_frame<W: Write>(w: &mut W, proof: &GrapevineProof) -> Result<(), GrapevineError> {
+    let bytes = proof.to_bytes()?;
+    // never write a frame `read_proof_frame` would refuse
+    if bytes.len() > MAX_PROOF_FRAME_LEN {
+        return Err(GrapevineError::SerializationError("Proof too large to frame".to_string()));
+    }
+    w.write_all(&(bytes.len() as u32).to_be_bytes())
+        .and_then(|_| w.write_all(&bytes))
+        .map_err(|e| GrapevineError::SerializationError(e.to_string()))
+}
+
+/**
+ * Reads the next length-prefixed proof from a stream
+ * @dev the length prefix is untrusted, so frames longer than MAX_PROOF_FRAME_LEN are rejected
+ *      before anything is allocated for them
+ *
+ * @param r - the stream to read the frame from
+ * @return - the proof in the frame
+ */
+pub fn read_proof_frame<R: Read>(r: &mut R) -> Result<GrapevineProof, GrapevineError> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)
+        .map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_PROOF_FRAME_LEN {
+        return Err(GrapevineError::SerializationError(format!(
+            "Proof frame of {} bytes exceeds the maximum of {}",
+            len, MAX_PROOF_FRAME_LEN
+        )));
+    }
+    let mut bytes = vec![0u8; len];
+    r.read_exact(&mut bytes)
+        .map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+    GrapevineProof::from_bytes(&bytes)
+}
+
+/**
+ * Computes the number of public inputs the decider exposes for a given state width
+ * @dev use to size the calldata of an on-chain verifier (one uint256 word per input)
+ *
+ * @param state_len - the number of elements in the ivc state
+ * @return - the step counter plus the initial and final states
+ */
+pub fn decider_public_input_count(state_len: usize) -> usize {
+    1 + 2 * state_len
+}
+
+// converts a field element to u64, erroring if it does not fit
+pub fn fr_to_u64(value: Fr) -> Result<u64, GrapevineError> {
+    let bigint = value.into_bigint();
+    let limbs = bigint.as_ref();
+    if limbs[1..].iter().any(|limb| *limb != 0) {
+        return Err(GrapevineError::FieldConversion(
+            "Field element does not fit in u64".to_string(),
+        ));
+    }
+    Ok(limbs[0])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova::{prove_chain, replay_native, GrapevineProver};
+    use crate::params::test_nova_setup;
+    use crate::test_utils::{degree_input, sample_chain, sample_circuit, AUTH_SECRETS, USERNAMES};
+    use crate::test_utils::PHRASE;
+    use crate::utils::hash::phrase_hash;
+    use crate::utils::inputs::{get_z0, serialize_phrase};
+    use std::fs::File;
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn test_same_claim() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let degree_1 = degree_input(1);
+        let degree_2 = degree_input(2);
+
+        // prove the same degree 1 chain twice (chaff randomness differs between runs)
+        let mut prover_a =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), get_z0().to_vec()).unwrap();
+        prover_a.add_degree(degree_1.clone()).unwrap();
+        let proof_a = prover_a.finalize().unwrap();
+        let mut prover_b =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover_b.add_degree(degree_1).unwrap();
+        let proof_b = prover_b.finalize().unwrap();
+        verify(&verifier_params, &proof_a).unwrap();
+        verify(&verifier_params, &proof_b).unwrap();
+        assert!(proof_a.same_claim(&proof_b));
+
+        // extend one chain to degree 2
+        prover_a.add_degree(degree_2).unwrap();
+        let proof_c = prover_a.finalize().unwrap();
+        assert!(!proof_a.same_claim(&proof_c));
+    }
+
+    #[test]
+    fn test_grapevine_instances() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let z_0 = get_z0().to_vec();
+        let mut prover = GrapevineProver::new(&prover_params, f_circuit, z_0.clone()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+
+        let instances = GrapevineInstances::from(prover.folding_scheme());
+        instances
+            .verify(&verifier_params, z_0, prover.state(), Fr::from(2u32))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_verify_from_reader() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        let bytes = prover.finalize().unwrap().to_bytes().unwrap();
+
+        // verify from an in-memory reader
+        verify_from_reader(&verifier_params, Cursor::new(&bytes)).unwrap();
+
+        // verify from a file
+        let path = std::env::temp_dir().join("grapevine_verify_from_reader.bin");
+        std::fs::write(&path, &bytes).unwrap();
+        let file = BufReader::new(File::open(&path).unwrap());
+        verify_from_reader(&verifier_params, file).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_proof_frames() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+
+        // collect a proof after each of three degrees
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        let mut proofs = vec![];
+        for degree in 1..=3 {
+            prover.add_degree(degree_input(degree)).unwrap();
+            proofs.push(prover.finalize().unwrap());
+        }
+
+        // write all frames to a single buffer
+        let mut buffer = vec![];
+        for proof in proofs.iter() {
+            write_proof_frame(&mut buffer, proof).unwrap();
+        }
+
+        // read the frames back in order
+        let mut reader = Cursor::new(buffer);
+        for proof in proofs.iter() {
+            let read = read_proof_frame(&mut reader).unwrap();
+            assert!(read.same_claim(proof));
+            verify(&verifier_params, &read).unwrap();
+        }
+        assert!(read_proof_frame(&mut reader).is_err());
+
+        // an oversized length prefix is rejected without reading or allocating the frame
+        let oversized = ((MAX_PROOF_FRAME_LEN + 1) as u32).to_be_bytes();
+        assert!(matches!(
+            read_proof_frame(&mut Cursor::new(oversized)),
+            Err(GrapevineError::SerializationError(_))
+        ));
+        let huge = u32::MAX.to_be_bytes();
+        assert!(matches!(
+            read_proof_frame(&mut Cursor::new(huge)),
+            Err(GrapevineError::SerializationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_phrase_hash() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        let proof = prover.finalize().unwrap();
+
+        let phrase_hash = proof.z_i[PHRASE_HASH_INDEX];
+        assert!(verify_phrase_hash(&proof, phrase_hash).is_ok());
+        assert!(matches!(
+            verify_phrase_hash(&proof, phrase_hash + Fr::from(1)),
+            Err(GrapevineError::PhraseHashMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_params_compatible() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        let mut proof = prover.finalize().unwrap();
+        assert!(check_proof_params_compatible(&proof, &verifier_params).is_ok());
+
+        // a proof claiming different params is rejected before verification
+        proof.params_digest[0] ^= 1;
+        assert!(matches!(
+            check_proof_params_compatible(&proof, &verifier_params),
+            Err(GrapevineError::ParamsMismatch)
+        ));
+        assert!(matches!(
+            verify(&verifier_params, &proof),
+            Err(GrapevineError::ParamsMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_verify_cached() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        let proof = prover.finalize().unwrap();
+
+        // the first verification runs in full and is cached
+        let cache = VerificationCache::new();
+        verify_cached(&cache, &proof, &verifier_params).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.hits(), 0);
+
+        // an identical proof is served from the cache
+        let same = GrapevineProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+        verify_cached(&cache, &same, &verifier_params).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.hits(), 1);
+
+        // a proof with the same claim but different bytes is not
+        let mut tampered = proof.clone();
+        tampered.params_digest[0] ^= 1;
+        assert!(verify_cached(&cache, &tampered, &verifier_params).is_err());
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn test_verify_extends() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        let prior = prover.finalize().unwrap();
+
+        // a segment folded from the prior final state continues the chain
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit.clone(), prior.z_i.clone()).unwrap();
+        prover.add_degree(degree_input(2)).unwrap();
+        let extension = prover.finalize().unwrap();
+        assert!(verify_extends(&prior, &extension).is_ok());
+
+        // a segment folded from any other state does not
+        let mut z_0 = prior.z_i.clone();
+        z_0[DEGREE_INDEX] += Fr::from(5);
+        let mut prover = GrapevineProver::new(&prover_params, f_circuit, z_0).unwrap();
+        prover.add_degree(degree_input(2)).unwrap();
+        let discontinuous = prover.finalize().unwrap();
+        assert!(matches!(
+            verify_extends(&prior, &discontinuous),
+            Err(GrapevineError::ChainDiscontinuity(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_step_parity() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        assert!(prover.finalize().unwrap().validate_step_parity().is_ok());
+
+        // a logic step without its chaff step
+        prover.prove_step(degree_input(2)).unwrap();
+        let proof = prover.finalize().unwrap();
+        assert!(matches!(
+            proof.validate_step_parity(),
+            Err(GrapevineError::OddStepCount(3))
+        ));
+        assert!(matches!(
+            verify(&verifier_params, &proof),
+            Err(GrapevineError::OddStepCount(3))
+        ));
+    }
+
+    #[test]
+    fn test_decider_public_input_count() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut prover =
+            GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+        prover.add_degree(degree_input(1)).unwrap();
+        let proof = prover.finalize().unwrap();
+
+        let public_inputs = proof.public_inputs();
+        assert_eq!(public_inputs.len(), decider_public_input_count(proof.z_i.len()));
+        assert_eq!(public_inputs[0], Fr::from(proof.num_steps as u64));
+        assert_eq!(&public_inputs[1..5], proof.z_0.as_slice());
+    }
+
+    #[test]
+    fn test_verify_and_extract() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, z_0) = sample_chain(2);
+        let proof = prove_chain(&prover_params, f_circuit.clone(), &inputs).unwrap();
+
+        // the claim matches the state the inputs produce natively
+        let z_i = replay_native(&f_circuit, z_0.to_vec(), &inputs).unwrap();
+        let claim = verify_and_extract(&verifier_params, &proof).unwrap();
+        assert_eq!(
+            claim,
+            GrapevineClaim {
+                root: z_i[SECRET_HASH_INDEX],
+                degree: fr_to_u64(z_i[DEGREE_INDEX]).unwrap(),
+                phrase_hash: z_i[PHRASE_HASH_INDEX],
+            }
+        );
+
+        // nothing is extracted from a proof that does not verify
+        let mut tampered = proof.clone();
+        tampered.z_i[SECRET_HASH_INDEX] += Fr::from(1);
+        assert!(verify_and_extract(&verifier_params, &tampered).is_err());
+
+        // a proof folded from a forged starting state verifies but attests to nothing
+        let z_0 = vec![Fr::from(100), Fr::from(1), Fr::from(2), Fr::from(0)];
+        let mut prover = GrapevineProver::new(&prover_params, f_circuit, z_0).unwrap();
+        prover.add_degree(degree_input(2)).unwrap();
+        let forged = prover.finalize().unwrap();
+        verify(&verifier_params, &forged).unwrap();
+        assert!(matches!(
+            verify_and_extract(&verifier_params, &forged),
+            Err(GrapevineError::NotFreshStart)
+        ));
+        assert!(prove_degree_at_least(&verifier_params, &forged, 1).is_err());
+    }
+
+    #[test]
+    fn test_verify_against_commitments() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(2);
+        let proof = prove_chain(&prover_params, f_circuit, &inputs).unwrap();
+
+        let usernames = USERNAMES[..2].to_vec();
+        let secrets = AUTH_SECRETS[..2].to_vec();
+        let commitments = usernames
+            .iter()
+            .zip(&secrets)
+            .map(|(username, secret)| {
+                identity_commitment(proof.phrase_hash(), username, secret).unwrap()
+            })
+            .collect::<Vec<Fr>>();
+        verify_against_commitments(&verifier_params, &proof, &usernames, &secrets, &commitments)
+            .unwrap();
+
+        // a revealed secret that does not open the published commitment is caught
+        let mut wrong_secrets = secrets.clone();
+        wrong_secrets[1] += 1;
+        assert!(matches!(
+            verify_against_commitments(
+                &verifier_params,
+                &proof,
+                &usernames,
+                &wrong_secrets,
+                &commitments
+            ),
+            Err(GrapevineError::VerificationFailed(msg)) if msg.contains("identity 1")
+        ));
+
+        // as are commitments for a different path
+        let mut swapped = commitments.clone();
+        swapped.swap(0, 1);
+        assert!(
+            verify_against_commitments(&verifier_params, &proof, &usernames, &secrets, &swapped)
+                .is_err()
+        );
+
+        // every identity needs a commitment
+        assert!(matches!(
+            verify_against_commitments(
+                &verifier_params,
+                &proof,
+                &usernames,
+                &secrets,
+                &commitments[..1]
+            ),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_prove_degree_at_least() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(2);
+        let proof = prove_chain(&prover_params, f_circuit, &inputs).unwrap();
+        let degree = proof.degree().unwrap();
+
+        // thresholds at or below the degree are met, above it are not
+        for threshold in [0, degree] {
+            let attestation = prove_degree_at_least(&verifier_params, &proof, threshold).unwrap();
+            assert_eq!(attestation, DegreeAttestation { threshold, satisfied: true });
+        }
+        let attestation = prove_degree_at_least(&verifier_params, &proof, degree + 1).unwrap();
+        assert!(!attestation.satisfied);
+
+        // nothing is attested for a proof that does not verify
+        let mut tampered = proof.clone();
+        tampered.z_i[SECRET_HASH_INDEX] += Fr::from(1);
+        assert!(prove_degree_at_least(&verifier_params, &tampered, 0).is_err());
+    }
+
+    #[test]
+    fn test_prove_not_linked() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(3);
+        let proof = prove_chain(&prover_params, f_circuit.clone(), &inputs).unwrap();
+        let phrase_hash = phrase_hash(&serialize_phrase(&PHRASE).unwrap()).unwrap();
+
+        // an identity outside the chain is not linked
+        let outsider =
+            identity_commitment(phrase_hash, &USERNAMES[5], &AUTH_SECRETS[5]).unwrap();
+        prove_not_linked(&f_circuit, &proof, &inputs, outsider).unwrap();
+
+        // any identity within it is
+        let member = identity_commitment(phrase_hash, &USERNAMES[1], &AUTH_SECRETS[1]).unwrap();
+        assert!(matches!(
+            prove_not_linked(&f_circuit, &proof, &inputs, member),
+            Err(GrapevineError::VerificationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_summary() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(2);
+        let proof = prove_chain(&prover_params, f_circuit, &inputs).unwrap();
+
+        let summary = proof.summary();
+        let root = proof.root().into_bigint().to_bytes_be();
+        let truncated = format!("0x{:02x}{:02x}{:02x}{:02x}...", root[0], root[1], root[2], root[3]);
+        assert!(summary.starts_with("Grapevine proof: "));
+        assert!(summary.contains(&format!("degree {}", proof.degree().unwrap())));
+        assert!(summary.contains(&truncated));
+        assert!(summary.contains("4 steps"));
+    }
+
+    #[test]
+    fn test_verify_batch_with_threads() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let mut proofs = (1..=3)
+            .map(|degree| {
+                let (inputs, _) = sample_chain(degree);
+                prove_chain(&prover_params, f_circuit.clone(), &inputs).unwrap()
+            })
+            .collect::<Vec<GrapevineProof>>();
+        proofs[1].z_i[SECRET_HASH_INDEX] += Fr::from(1);
+
+        // each proof gets its own result, in order
+        let results = verify_batch_with_threads(&verifier_params, &proofs, 2).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok() && results[1].is_err() && results[2].is_ok());
+
+        // a pool needs at least one thread
+        assert!(verify_batch_with_threads(&verifier_params, &proofs, 0).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let f_circuit = sample_circuit();
+        let (prover_params, _) = test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(1);
+        let bytes = prove_chain(&prover_params, f_circuit, &inputs).unwrap().to_bytes().unwrap();
+        assert_eq!(bytes[0], PROOF_FORMAT_VERSION);
+
+        // a proof from a newer build is rejected cleanly rather than misparsed
+        let mut bumped = bytes.clone();
+        bumped[0] = PROOF_FORMAT_VERSION + 1;
+        assert!(matches!(
+            GrapevineProof::from_bytes(&bumped),
+            Err(GrapevineError::UnsupportedVersion { found, supported: (1, PROOF_FORMAT_VERSION) })
+                if found == PROOF_FORMAT_VERSION + 1
+        ));
+        assert!(GrapevineProof::from_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_attestation() {
+        let claim = GrapevineClaim {
+            root: Fr::from(7),
+            degree: 3,
+            phrase_hash: Fr::from(11),
+        };
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let attestation = claim.attest(&signing_key);
+        assert_eq!(attestation.claim, claim);
+        attestation.verify(&signing_key.verifying_key()).unwrap();
+
+        // any change to the claim invalidates the signature
+        let mut tampered = attestation.clone();
+        tampered.claim.degree = 2;
+        assert!(matches!(
+            tampered.verify(&signing_key.verifying_key()),
+            Err(GrapevineError::VerificationFailed(_))
+        ));
+        let mut tampered = attestation.clone();
+        tampered.claim.root += Fr::from(1);
+        assert!(tampered.verify(&signing_key.verifying_key()).is_err());
+
+        // as does checking it against another verifier's key
+        let other_key = SigningKey::from_bytes(&[2u8; 32]);
+        assert!(attestation.verify(&other_key.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn test_to_verifiable_credential() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(1);
+        let proof = prove_chain(&prover_params, f_circuit, &inputs).unwrap();
+        let claim = verify_and_extract(&verifier_params, &proof).unwrap();
+        let vc = claim.to_verifiable_credential("did:example:issuer", &proof).unwrap();
+
+        assert_eq!(vc["@context"][0], serde_json::json!(VC_CONTEXT));
+        assert_eq!(vc["type"][0], serde_json::json!("VerifiableCredential"));
+        assert_eq!(vc["issuer"], serde_json::json!("did:example:issuer"));
+        let subject = &vc["credentialSubject"];
+        assert_eq!(subject["degree"], serde_json::json!(claim.degree));
+        let root = CircomWrapper::<Fr>::ark_primefield_to_num_bigint(claim.root).to_string();
+        assert_eq!(subject["root"], serde_json::json!(root));
+        assert!(subject["phraseHash"].is_string());
+
+        // the embedded proof decodes back to the proof the claim was extracted from
+        let proof_value = vc["proof"]["proofValue"].as_str().unwrap();
+        let hex = proof_value.strip_prefix('f').unwrap();
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect::<Vec<u8>>();
+        assert_eq!(bytes, proof.to_bytes().unwrap());
+        assert!(GrapevineProof::from_bytes(&bytes).unwrap().same_claim(&proof));
+    }
+
+    #[test]
+    fn test_verify_self_contained() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+        let (inputs, _) = sample_chain(1);
+        let proof = prove_chain(&prover_params, f_circuit, &inputs).unwrap();
+
+        // a proof must embed its params to be verified self contained
+        assert!(matches!(
+            verify_self_contained(&proof, &verifier_params),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+
+        // the embedded digests survive serialization and match the local params
+        let embedded = proof.clone().with_embedded_params(&verifier_params);
+        let embedded = GrapevineProof::from_bytes(&embedded.to_bytes().unwrap()).unwrap();
+        verify_self_contained(&embedded, &verifier_params).unwrap();
+
+        // a mismatched poseidon config or r1cs digest is rejected before verifying
+        let mut mismatched = embedded.clone();
+        mismatched.poseidon_digest = Some([0u8; 32]);
+        assert!(matches!(
+            verify_self_contained(&mismatched, &verifier_params),
+            Err(GrapevineError::ParamsMismatch)
+        ));
+        let mut mismatched = embedded.clone();
+        mismatched.params_digest[0] ^= 1;
+        assert!(matches!(
+            verify_self_contained(&mismatched, &verifier_params),
+            Err(GrapevineError::ParamsMismatch)
+        ));
+
+        // version 1 proofs, which predate the embedded digest, still deserialize
+        let bytes = proof.to_bytes().unwrap();
+        let digest_offset = 1 + (8 + 32 * proof.z_0.len()) + (8 + 32 * proof.z_i.len()) + 8 + 32;
+        let mut v1 = bytes.clone();
+        v1[0] = 1;
+        assert_eq!(v1.remove(digest_offset), 0);
+        let decoded = GrapevineProof::from_bytes(&v1).unwrap();
+        assert!(decoded.poseidon_digest.is_none());
+        verify(&verifier_params, &decoded).unwrap();
+    }
+}
@@ -0,0 +1,317 @@
+use ark_ff::{BigInteger, PrimeField};
+use ark_pallas::Fr;
+use lazy_static::lazy_static;
+use num_bigint::{
+    BigInt,
+    Sign::{Minus, Plus},
+};
+use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, fields::fp::FpVar, R1CSVar};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use sonobe::{frontend::FCircuit, Error as SonobeError};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    circom::{GrapevineFCircuit, DEFAULT_STATE_LEN},
+    errors::GrapevineError,
+    nova::{GrapevineProver, GrapevineStepCircuit},
+    params::test_nova_setup,
+    utils::{
+        hash::phrase_hash,
+        inputs::{get_z0, random_f_bigint, reconstruct_inputs, serialize_phrase},
+        wrapper::{CircomPrivateInput, CircomWrapper},
+        CHAFF_INDEX, DEGREE_INDEX, PHRASE_HASH_INDEX,
+    },
+};
+
+// maximum degree the sample fixtures can build a chain for
+pub const MAX_SAMPLE_DEGREE: usize = 8;
+
+lazy_static! {
+    pub static ref R1CS_PATH: PathBuf = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+    pub static ref WASM_PATH: PathBuf = PathBuf::from("./circom/artifacts/grapevine.wasm");
+    pub static ref PHRASE: String = String::from("This is a secret");
+    pub static ref USERNAMES: [String; MAX_SAMPLE_DEGREE] = [
+        String::from("alice"),
+        String::from("bob"),
+        String::from("charlie"),
+        String::from("david"),
+        String::from("eve"),
+        String::from("frank"),
+        String::from("grace"),
+        String::from("heidi")
+    ];
+    pub static ref AUTH_SECRETS: [BigInt; MAX_SAMPLE_DEGREE] = (0..MAX_SAMPLE_DEGREE)
+        .map(|_| random_f_bigint::<Fr>())
+        .collect::<Vec<BigInt>>()
+        .try_into()
+        .unwrap();
+}
+
+// returns a grapevine function circuit over the sample artifacts
+pub fn sample_circuit() -> GrapevineFCircuit<Fr> {
+    GrapevineFCircuit::<Fr>::new((R1CS_PATH.clone(), WASM_PATH.clone()))
+}
+
+/**
+ * Builds the logic step input for a given degree of the sample chain
+ * @dev degree 1 reveals the phrase, degree N links USERNAMES[N - 2] to USERNAMES[N - 1]
+ *
+ * @param degree - the degree (1-indexed) to build the logic input for
+ * @return - the logic step input
+ */
+pub fn degree_input(degree: usize) -> CircomPrivateInput {
+    assert!(degree >= 1 && degree <= MAX_SAMPLE_DEGREE);
+    let (inputs, _) = sample_chain(degree);
+    inputs[2 * (degree - 1)].clone()
+}
+
+/**
+ * Builds a ready-to-fold sample chain
+ *
+ * @param degree - the degree of separation the chain should prove
+ * @return - the interleaved (logic, chaff) step inputs and the starting state
+ */
+pub fn sample_chain(degree: usize) -> (Vec<CircomPrivateInput>, [Fr; 4]) {
+    let inputs = reconstruct_inputs(&PHRASE, &sample_path(degree)).unwrap();
+    (inputs, get_z0())
+}
+
+// returns the (username, auth secret) path of the sample chain
+pub fn sample_path(degree: usize) -> Vec<(String, BigInt)> {
+    assert!(degree <= MAX_SAMPLE_DEGREE);
+    (0..degree)
+        .map(|i| (USERNAMES[i].clone(), AUTH_SECRETS[i].clone()))
+        .collect()
+}
+
+/**
+ * Asserts a chaff step leaves the meaningful state untouched
+ * @dev only the chaff flag may change across a chaff step
+ *
+ * @param f_circuit - the grapevine function circuit
+ * @param z_before - the state the chaff step is applied to
+ * @return - error with the index of the first slot the chaff step mutated
+ */
+pub fn assert_chaff_is_noop(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    z_before: &[Fr],
+) -> Result<(), GrapevineError> {
+    let mut f_circuit = f_circuit.clone();
+    f_circuit.set_private_input(CircomPrivateInput::empty(true));
+    // fold at an odd index, where chaff steps belong
+    let z_after = f_circuit
+        .step_native(1, z_before.to_vec())
+        .map_err(|e| GrapevineError::FoldingError(e.to_string()))?;
+    for (index, (before, after)) in z_before.iter().zip(z_after.iter()).enumerate() {
+        if index != CHAFF_INDEX && before != after {
+            return Err(GrapevineError::ChaffMutatedState { index });
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Asserts the phrase hash the circuit emits after degree 1 matches the native phrase hash
+ * @dev the key cross-check of the phrase packing: a mismatch means the chunking convention
+ *      (leading zero byte, chunk order) or the hash itself diverges between rust and circom
+ *
+ * @param f_circuit - the grapevine function circuit
+ * @param phrase - the phrase revealed at degree 1
+ * @return - the phrase hash if both sides agree, PhraseHashMismatch otherwise
+ */
+pub fn assert_phrase_hash_matches_circuit(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    phrase: &String,
+) -> Result<Fr, GrapevineError> {
+    let chunks =
+        serialize_phrase(phrase).map_err(|e| GrapevineError::InvalidInput(e.to_string()))?;
+    let native = phrase_hash(&chunks)?;
+
+    let path = [(USERNAMES[0].clone(), AUTH_SECRETS[0].clone())];
+    let mut f_circuit = f_circuit.clone();
+    f_circuit.set_private_input(reconstruct_inputs(phrase, &path)?[0].clone());
+    let z_1 = f_circuit
+        .step_native(0, get_z0().to_vec())
+        .map_err(|e| GrapevineError::FoldingError(e.to_string()))?;
+    if z_1[PHRASE_HASH_INDEX] != native {
+        return Err(GrapevineError::PhraseHashMismatch);
+    }
+    Ok(native)
+}
+
+/**
+ * Measures the time each additional degree adds to proving the sample chain
+ * @dev folds the chain degree by degree on one prover, timing each logic and chaff step pair.
+ *      Nova folds every step into a running instance of fixed size, so the marginal cost should
+ *      stay flat however long the chain grows. A rising trend points at witness generation or
+ *      the circuit rather than the folding
+ *
+ * @param max_degree - the degree to fold the sample chain up to
+ * @return - the time added by each degree, starting at degree 1
+ */
+pub fn measure_marginal_cost(max_degree: usize) -> Vec<Duration> {
+    let f_circuit = sample_circuit();
+    let (prover_params, _) = test_nova_setup(f_circuit.clone());
+    let mut prover =
+        GrapevineProver::new(&prover_params, f_circuit, get_z0().to_vec()).unwrap();
+    (1..=max_degree)
+        .map(|degree| {
+            let input = degree_input(degree);
+            let start = Instant::now();
+            prover.add_degree(input).unwrap();
+            start.elapsed()
+        })
+        .collect()
+}
+
+// number of equal width buckets the field is split into when checking chaff uniformity
+const UNIFORMITY_BUCKETS: usize = 16;
+
+/**
+ * Asserts the random values marshalled for chaff steps are uniform over [0, MODULUS)
+ * @dev buckets the sampled values by magnitude and requires every bucket to hold within 20%
+ *      of its expected share. Each chaff step marshals 10 random values
+ *
+ * @param samples - the number of chaff steps to marshal
+ * @return - error with the first bucket whose count is out of tolerance
+ */
+pub fn assert_chaff_uniform(samples: usize) -> Result<(), GrapevineError> {
+    let modulus = BigInt::from_bytes_be(Plus, &Fr::MODULUS.to_bytes_be());
+    let mut counts = [0usize; UNIFORMITY_BUCKETS];
+    let mut drawn = 0;
+    for _ in 0..samples {
+        let marshalled =
+            CircomWrapper::<Fr>::marshal_private_inputs(&CircomPrivateInput::empty(true))?;
+        for value in marshalled.into_iter().flat_map(|(_, values)| values) {
+            if value.sign() == Minus || value >= modulus {
+                return Err(GrapevineError::FieldConversion(
+                    "Chaff value is outside of the field".to_string(),
+                ));
+            }
+            let bucket: usize = (value * UNIFORMITY_BUCKETS / &modulus).try_into().unwrap_or(0);
+            counts[bucket] += 1;
+            drawn += 1;
+        }
+    }
+    let expected = drawn / UNIFORMITY_BUCKETS;
+    let tolerance = expected / 5;
+    match counts.iter().position(|count| count.abs_diff(expected) > tolerance) {
+        Some(bucket) => Err(GrapevineError::ChaffNotUniform {
+            bucket,
+            count: counts[bucket],
+            expected,
+        }),
+        None => Ok(()),
+    }
+}
+
+/**
+ * In-process stand-in for the grapevine circuit for fast orchestration tests
+ * @dev increments the degree on logic steps and leaves the state untouched on chaff, with no
+ *      circom artifacts or wasm involved. An external input, if set, is written to the chaff
+ *      slot so tests can observe which input each step saw
+ */
+#[derive(Clone, Debug)]
+pub struct MockFCircuit {
+    // whether the pending step is chaff, None until an input is set
+    chaff: Option<bool>,
+    // the external input of the pending step, written to the chaff slot when set
+    external_input: Option<Fr>,
+    // the circuit version reported in the fingerprint, to stand in for upgraded artifacts
+    version: u8,
+    // the step index step_native fails at, to stand in for a step that cannot be folded
+    fail_at: Option<usize>,
+}
+
+impl MockFCircuit {
+    // reports a different circuit version (fingerprint) without changing the constraints
+    pub fn with_version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    // fails natively computing the step at the given index
+    pub fn with_failure_at(mut self, step: usize) -> Self {
+        self.fail_at = Some(step);
+        self
+    }
+}
+
+impl FCircuit<Fr> for MockFCircuit {
+    type Params = ();
+
+    fn new(_params: Self::Params) -> Self {
+        Self {
+            chaff: None,
+            external_input: None,
+            version: 1,
+            fail_at: None,
+        }
+    }
+
+    fn state_len(&self) -> usize {
+        DEFAULT_STATE_LEN
+    }
+
+    fn step_native(&self, i: usize, z_i: Vec<Fr>) -> Result<Vec<Fr>, SonobeError> {
+        if self.fail_at == Some(i) {
+            return Err(SonobeError::Other(format!("Mock failure at step {}", i)));
+        }
+        let chaff = self
+            .chaff
+            .ok_or(SonobeError::Other(GrapevineError::InputsEmpty.to_string()))?;
+        let mut z_i1 = z_i;
+        if !chaff {
+            z_i1[DEGREE_INDEX] += Fr::from(1);
+        }
+        if let Some(external_input) = self.external_input {
+            z_i1[CHAFF_INDEX] = external_input;
+        }
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        _i: usize,
+        z_i: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        // shape synthesis (setup, init) runs without an input and is synthesized as chaff
+        let chaff = self.chaff.unwrap_or(true);
+        let mut z_i1 = z_i;
+        // the step kind is a boolean witness so logic and chaff steps share one R1CS shape
+        let is_logic = Boolean::new_witness(cs.clone(), || Ok(!chaff))?;
+        z_i1[DEGREE_INDEX] = z_i1[DEGREE_INDEX].clone() + FpVar::from(is_logic);
+        // the slot is always a fresh witness so that every step has the same R1CS shape
+        let slot = z_i1[CHAFF_INDEX].clone();
+        z_i1[CHAFF_INDEX] = FpVar::new_witness(cs, || match self.external_input {
+            Some(external_input) => Ok(external_input),
+            None => slot.value(),
+        })?;
+        Ok(z_i1)
+    }
+}
+
+impl GrapevineStepCircuit for MockFCircuit {
+    fn set_private_input(&mut self, input: CircomPrivateInput) {
+        self.chaff = Some(input.is_chaff());
+    }
+
+    fn clear_private_input(&mut self) {
+        self.chaff = None;
+    }
+
+    fn set_external_input(&mut self, external_input: Vec<BigInt>) -> Result<(), GrapevineError> {
+        let external_input = CircomWrapper::<Fr>::witness_to_field(&external_input)
+            .map_err(|e| GrapevineError::FieldConversion(e.to_string()))?;
+        self.external_input = external_input.first().copied();
+        Ok(())
+    }
+
+    fn circuit_fingerprint(&self) -> Result<String, GrapevineError> {
+        Ok(format!("mock-v{}", self.version))
+    }
+}
@@ -0,0 +1,504 @@
+use ark_crypto_primitives::{
+    crh::{poseidon::CRH, CRHScheme},
+    sponge::poseidon::PoseidonConfig,
+};
+use ark_ff::PrimeField;
+use ark_pallas::Fr;
+use num_bigint::BigInt;
+use sonobe::transcript::poseidon::poseidon_test_config;
+
+use super::{
+    inputs::{bigint_to_f, serialize_username, validate_input_sequence},
+    wrapper::{CircomPrivateInput, CircomWrapper},
+    SECRET_FIELD_LENGTH,
+};
+use crate::errors::GrapevineError;
+
+/**
+ * Domain separation tags prepended to every native Poseidon hash
+ * @dev the tag is absorbed as the first field element so identical preimages hashed for
+ *      different purposes can never collide:
+ *        - PhraseHash = 1
+ *        - IdentityCommitment = 2
+ *        - Nullifier = 3
+ *        - StateDigest = 4
+ *        - Accumulator = 5
+ *        - ChainCommitment = 6
+ *        - ChaffSeed = 7
+ *      tagged hashes are native-only: grapevine.circom does not absorb a tag, so none of these
+ *      values can be compared against a hash computed by the circuit
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DomainTag {
+    PhraseHash = 1,
+    IdentityCommitment = 2,
+    Nullifier = 3,
+    StateDigest = 4,
+    Accumulator = 5,
+    ChainCommitment = 6,
+    ChaffSeed = 7,
+}
+
+impl DomainTag {
+    // returns the tag as a field element
+    pub fn to_field(&self) -> Fr {
+        Fr::from(*self as u64)
+    }
+}
+
+/**
+ * Poseidon hashes field elements under a domain separation tag
+ *
+ * @param tag - the domain the hash is computed for
+ * @param inputs - the preimage
+ * @return - H(tag, inputs...)
+ */
+pub fn poseidon_hash(tag: DomainTag, inputs: &[Fr]) -> Result<Fr, GrapevineError> {
+    let mut preimage = vec![tag.to_field()];
+    preimage.extend_from_slice(inputs);
+    CRH::<Fr>::evaluate(&poseidon_test_config::<Fr>(), preimage)
+        .map_err(|e| GrapevineError::HashError(e.to_string()))
+}
+
+/**
+ * Poseidon hashes field elements the way circomlib's `Poseidon(n)` template does
+ * @dev untagged. The state starts as [0, inputs...] and the first element is output after the
+ *      permutation, where the sponge behind `poseidon_hash` outputs the element after the
+ *      capacity. The config must be for width inputs.len() + 1
+ *
+ * @param config - the round constants, mds matrix and round numbers to hash with
+ * @param inputs - the preimage
+ * @return - Poseidon(inputs...) as computed by circomlib under the config
+ */
+pub fn circomlib_poseidon_hash<F: PrimeField>(
+    config: &PoseidonConfig<F>,
+    inputs: &[F],
+) -> Result<F, GrapevineError> {
+    let width = inputs.len() + 1;
+    let rounds = config.full_rounds + config.partial_rounds;
+    if config.mds.len() != width
+        || config.ark.len() != rounds
+        || config.ark.iter().chain(config.mds.iter()).any(|row| row.len() != width)
+    {
+        return Err(GrapevineError::HashError(format!(
+            "Poseidon config does not have width {}",
+            width
+        )));
+    }
+    let mut state = vec![F::zero()];
+    state.extend_from_slice(inputs);
+    for (round, constants) in config.ark.iter().enumerate() {
+        for (value, constant) in state.iter_mut().zip(constants) {
+            *value += constant;
+        }
+        // full rounds apply the s-box to every element, partial rounds to the first only
+        let partial = round >= config.full_rounds / 2
+            && round < config.full_rounds / 2 + config.partial_rounds;
+        let sboxed = if partial { 1 } else { width };
+        for value in state.iter_mut().take(sboxed) {
+            *value = value.pow([config.alpha]);
+        }
+        state = config
+            .mds
+            .iter()
+            .map(|row| row.iter().zip(state.iter()).map(|(m, value)| *m * value).sum())
+            .collect();
+    }
+    Ok(state[0])
+}
+
+/**
+ * Computes the hash of a serialized phrase
+ *
+ * @param phrase - the phrase serialized into field-sized chunks
+ * @return - the phrase hash
+ */
+pub fn phrase_hash(phrase: &[BigInt; SECRET_FIELD_LENGTH]) -> Result<Fr, GrapevineError> {
+    let chunks = phrase
+        .iter()
+        .map(bigint_to_f::<Fr>)
+        .collect::<Result<Vec<Fr>, GrapevineError>>()?;
+    poseidon_hash(DomainTag::PhraseHash, &chunks)
+}
+
+/**
+ * Computes the identity commitment binding a username and auth secret to a phrase hash
+ *
+ * @param phrase_hash - the hash of the phrase the chain is about
+ * @param username - the username of the identity
+ * @param auth_secret - the auth secret of the identity
+ * @return - H(phrase_hash, username, auth_secret)
+ */
+pub fn identity_commitment(
+    phrase_hash: Fr,
+    username: &String,
+    auth_secret: &BigInt,
+) -> Result<Fr, GrapevineError> {
+    let username = serialize_username(username)
+        .map_err(|e| GrapevineError::InvalidInput(e.to_string()))?;
+    poseidon_hash(
+        DomainTag::IdentityCommitment,
+        &[phrase_hash, bigint_to_f(&username)?, bigint_to_f(auth_secret)?],
+    )
+}
+
+/**
+ * Computes the nullifier of an auth secret for a given phrase
+ *
+ * @param phrase_hash - the hash of the phrase the chain is about
+ * @param auth_secret - the auth secret being nullified
+ * @return - H(phrase_hash, auth_secret)
+ */
+pub fn nullifier(phrase_hash: Fr, auth_secret: &BigInt) -> Result<Fr, GrapevineError> {
+    poseidon_hash(DomainTag::Nullifier, &[phrase_hash, bigint_to_f(auth_secret)?])
+}
+
+/**
+ * Computes a digest of an ivc state
+ *
+ * @param z - the ivc state
+ * @return - H(z...)
+ */
+pub fn state_digest(z: &[Fr]) -> Result<Fr, GrapevineError> {
+    poseidon_hash(DomainTag::StateDigest, z)
+}
+
+/**
+ * Checks the auth secrets of two adjacent degrees reproduce the degree secret hashes they link
+ * @dev degree N's logic step recomputes degree N - 1's secret hash from (usernames[0],
+ *      auth_secrets[0]) before emitting its own from (usernames[1], auth_secrets[1])
+ *
+ * @param phrase_hash - the hash of the phrase the chain is about
+ * @param prev_secret - the auth secret of the previous degree
+ * @param prev_username - the username of the previous degree
+ * @param next_secret - the auth secret of the next degree
+ * @param next_username - the username of the next degree
+ * @param secret_hashes - the degree secret hashes emitted by the previous and next degrees
+ * @return - true if both identities reproduce their degree secret hash
+ */
+pub fn verify_link(
+    phrase_hash: Fr,
+    prev_secret: &BigInt,
+    prev_username: &str,
+    next_secret: &BigInt,
+    next_username: &str,
+    secret_hashes: [Fr; 2],
+) -> Result<bool, GrapevineError> {
+    let prev = identity_commitment(phrase_hash, &prev_username.to_string(), prev_secret)?;
+    let next = identity_commitment(phrase_hash, &next_username.to_string(), next_secret)?;
+    Ok(prev == secret_hashes[0] && next == secret_hashes[1])
+}
+
+/**
+ * Replays the accumulator chain over a path of identities natively
+ * @dev acc_i = H(acc_{i-1}, username_i, auth_secret_i) starting from z0_accumulator
+ *
+ * @param z0_accumulator - the accumulator value in the starting state
+ * @param path - the (username, auth secret) of each degree in order
+ * @return - the accumulator after absorbing every identity in the path
+ */
+pub fn compute_accumulator(
+    z0_accumulator: Fr,
+    path: &[(String, BigInt)],
+) -> Result<Fr, GrapevineError> {
+    path.iter()
+        .try_fold(z0_accumulator, |accumulator, (username, auth_secret)| {
+            let username = serialize_username(username)
+                .map_err(|e| GrapevineError::InvalidInput(e.to_string()))?;
+            poseidon_hash(
+                DomainTag::Accumulator,
+                &[accumulator, bigint_to_f(&username)?, bigint_to_f(auth_secret)?],
+            )
+        })
+}
+
+/**
+ * Lists the identity commitment each logic step of a chain introduces, e.g. as Merkle leaves
+ * @dev chaff and padding steps are skipped. The phrase hash is taken from the first step
+ *
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @return - H(phrase_hash, username, auth_secret) of each degree in order
+ */
+pub fn degree_commitments(inputs: &[CircomPrivateInput]) -> Result<Vec<Fr>, GrapevineError> {
+    validate_input_sequence(inputs)?;
+    let first = inputs.first().ok_or(GrapevineError::InputsEmpty)?;
+    let phrase = first
+        .phrase()
+        .ok_or(GrapevineError::InvalidInput("First step does not reveal the phrase".to_string()))?
+        .serialize()?;
+    let phrase_hash = phrase_hash(&phrase)?;
+    inputs
+        .iter()
+        .filter(|input| !input.is_chaff())
+        .map(|input| match (&input.usernames()[1], &input.auth_secrets()[1]) {
+            (Some(username), Some(auth_secret)) => {
+                identity_commitment(phrase_hash, username, auth_secret)
+            }
+            _ => Err(GrapevineError::InvalidInput(
+                "Logic step is missing its identity".to_string(),
+            )),
+        })
+        .collect()
+}
+
+/**
+ * Checks whether an identity appears anywhere in a chain
+ * @dev the native, verifier-with-witness check behind `prove_not_linked`: whoever runs it
+ *      holds every identity of the chain. Proving the identity is absent without revealing the
+ *      chain needs a non-membership circuit, which is not implemented
+ *
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @param target - the identity commitment (see `identity_commitment`) to look for
+ * @return - true if a degree of the chain introduces the target identity
+ */
+pub fn is_identity_in_chain(
+    inputs: &[CircomPrivateInput],
+    target: Fr,
+) -> Result<bool, GrapevineError> {
+    Ok(degree_commitments(inputs)?.contains(&target))
+}
+
+/**
+ * Commits to the exact input sequence of a chain before it is proven (commit-reveal)
+ * @dev each step is encoded canonically as [kind, phrase chunks, usernames, auth secrets]
+ *      with absent values as 0 and kind 0 = logic, 1 = chaff, 2 = padding. The step count is
+ *      absorbed first so sequences of different lengths cannot collide
+ *
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @return - H(len, step_0..., step_1..., ...)
+ */
+pub fn commit_to_chain(inputs: &[CircomPrivateInput]) -> Result<Fr, GrapevineError> {
+    let mut preimage = vec![Fr::from(inputs.len() as u64)];
+    for input in inputs {
+        let kind = match (input.is_padding(), input.is_chaff()) {
+            (true, _) => 2u64,
+            (false, true) => 1,
+            (false, false) => 0,
+        };
+        preimage.push(Fr::from(kind));
+        match input.phrase() {
+            Some(phrase) => {
+                for chunk in phrase.serialize()?.iter() {
+                    preimage.push(bigint_to_f(chunk)?);
+                }
+            }
+            None => preimage.extend([Fr::from(0); SECRET_FIELD_LENGTH]),
+        }
+        for username in input.usernames() {
+            preimage.push(match username {
+                Some(username) => bigint_to_f(
+                    &serialize_username(username)
+                        .map_err(|e| GrapevineError::InvalidInput(e.to_string()))?,
+                )?,
+                None => Fr::from(0),
+            });
+        }
+        for auth_secret in input.auth_secrets() {
+            preimage.push(match auth_secret {
+                Some(auth_secret) => bigint_to_f(auth_secret)?,
+                None => Fr::from(0),
+            });
+        }
+    }
+    poseidon_hash(DomainTag::ChainCommitment, &preimage)
+}
+
+/**
+ * Commits to the seed deterministic chaff is drawn from, published before proving
+ * @dev the seed is absorbed as two 16 byte halves so it fits the field without reduction
+ *
+ * @param seed - the chaff seed (see `CircomPrivateInput::deterministic_chaff`)
+ * @return - H(seed[..16], seed[16..])
+ */
+pub fn chaff_seed_commitment(seed: &[u8; 32]) -> Result<Fr, GrapevineError> {
+    poseidon_hash(
+        DomainTag::ChaffSeed,
+        &[Fr::from_be_bytes_mod_order(&seed[..16]), Fr::from_be_bytes_mod_order(&seed[16..])],
+    )
+}
+
+/**
+ * Reconstructs the chaff a prover used at a step from their revealed seed, for audit
+ * @dev the seed must open the commitment the prover published before proving
+ *
+ * @param commitment - the published chaff seed commitment
+ * @param seed - the revealed chaff seed
+ * @param step - the index of the chaff step in the chain
+ * @return - the marshalled circom inputs of the chaff step
+ */
+pub fn reconstruct_chaff(
+    commitment: Fr,
+    seed: &[u8; 32],
+    step: usize,
+) -> Result<Vec<(String, Vec<BigInt>)>, GrapevineError> {
+    if chaff_seed_commitment(seed)? != commitment {
+        return Err(GrapevineError::VerificationFailed(
+            "Revealed seed does not open the chaff seed commitment".to_string(),
+        ));
+    }
+    CircomWrapper::<Fr>::marshal_private_inputs(&CircomPrivateInput::deterministic_chaff(
+        *seed, step,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::inputs::{reconstruct_inputs, serialize_phrase};
+
+    #[test]
+    fn test_domain_separation() {
+        let preimage = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let tags = [
+            DomainTag::PhraseHash,
+            DomainTag::IdentityCommitment,
+            DomainTag::Nullifier,
+            DomainTag::StateDigest,
+        ];
+        let hashes = tags
+            .iter()
+            .map(|tag| poseidon_hash(*tag, &preimage).unwrap())
+            .collect::<Vec<Fr>>();
+        for i in 0..hashes.len() {
+            for j in (i + 1)..hashes.len() {
+                assert_ne!(hashes[i], hashes[j]);
+            }
+        }
+
+        // the same tag and preimage is deterministic
+        assert_eq!(poseidon_hash(DomainTag::Nullifier, &preimage).unwrap(), hashes[2]);
+    }
+
+    #[test]
+    fn test_compute_accumulator() {
+        let path = vec![
+            (String::from("alice"), BigInt::from(1)),
+            (String::from("bob"), BigInt::from(2)),
+        ];
+
+        // matches chaining the accumulator one identity at a time
+        let step_1 = compute_accumulator(Fr::from(0), &path[..1]).unwrap();
+        let step_2 = compute_accumulator(step_1, &path[1..]).unwrap();
+        assert_eq!(compute_accumulator(Fr::from(0), &path).unwrap(), step_2);
+
+        // an empty path leaves the accumulator untouched and order matters
+        assert_eq!(compute_accumulator(Fr::from(7), &[]).unwrap(), Fr::from(7));
+        let reversed = path.iter().rev().cloned().collect::<Vec<_>>();
+        assert_ne!(compute_accumulator(Fr::from(0), &reversed).unwrap(), step_2);
+    }
+
+    #[test]
+    fn test_verify_link() {
+        let phrase_hash = Fr::from(42);
+        let (alice, bob) = (BigInt::from(1), BigInt::from(2));
+        let hashes = [
+            identity_commitment(phrase_hash, &String::from("alice"), &alice).unwrap(),
+            identity_commitment(phrase_hash, &String::from("bob"), &bob).unwrap(),
+        ];
+        assert!(verify_link(phrase_hash, &alice, "alice", &bob, "bob", hashes).unwrap());
+
+        // a tampered secret or swapped identity breaks the link
+        let tampered = BigInt::from(3);
+        assert!(!verify_link(phrase_hash, &alice, "alice", &tampered, "bob", hashes).unwrap());
+        assert!(!verify_link(phrase_hash, &bob, "bob", &alice, "alice", hashes).unwrap());
+    }
+
+    #[test]
+    fn test_commit_to_chain() {
+        let path = vec![
+            (String::from("alice"), BigInt::from(1)),
+            (String::from("bob"), BigInt::from(2)),
+        ];
+        let phrase = String::from("This is a secret");
+        let inputs = reconstruct_inputs(&phrase, &path).unwrap();
+        let commitment = commit_to_chain(&inputs).unwrap();
+
+        // an identical sequence matches
+        let again = reconstruct_inputs(&phrase, &path).unwrap();
+        assert_eq!(commit_to_chain(&again).unwrap(), commitment);
+
+        // reordering the steps, dropping one or changing a secret does not
+        let mut reordered = inputs.clone();
+        reordered.swap(0, 2);
+        assert_ne!(commit_to_chain(&reordered).unwrap(), commitment);
+        assert_ne!(commit_to_chain(&inputs[..2]).unwrap(), commitment);
+        let tampered = vec![path[0].clone(), (String::from("bob"), BigInt::from(3))];
+        let tampered = reconstruct_inputs(&phrase, &tampered).unwrap();
+        assert_ne!(commit_to_chain(&tampered).unwrap(), commitment);
+    }
+
+    #[test]
+    fn test_degree_commitments() {
+        let phrase = String::from("This is a secret");
+        let path = (0..3u64)
+            .map(|i| (format!("user{}", i), BigInt::from(i + 1)))
+            .collect::<Vec<_>>();
+        let inputs = reconstruct_inputs(&phrase, &path).unwrap();
+
+        // one commitment per degree, each binding that degree's identity to the phrase
+        let commitments = degree_commitments(&inputs).unwrap();
+        assert_eq!(commitments.len(), path.len());
+        let hash = phrase_hash(&serialize_phrase(&phrase).unwrap()).unwrap();
+        for ((username, auth_secret), commitment) in path.iter().zip(commitments.iter()) {
+            assert_eq!(identity_commitment(hash, username, auth_secret).unwrap(), *commitment);
+        }
+
+        // the list is deterministic and trailing padding adds nothing
+        assert_eq!(degree_commitments(&inputs).unwrap(), commitments);
+        let mut padded = inputs.clone();
+        padded.extend([CircomPrivateInput::padding(), CircomPrivateInput::padding()]);
+        assert_eq!(degree_commitments(&padded).unwrap(), commitments);
+    }
+
+    #[test]
+    fn test_is_identity_in_chain() {
+        let phrase = String::from("This is a secret");
+        let path = (0..3u64)
+            .map(|i| (format!("user{}", i), BigInt::from(i + 1)))
+            .collect::<Vec<_>>();
+        let inputs = reconstruct_inputs(&phrase, &path).unwrap();
+        let hash = phrase_hash(&serialize_phrase(&phrase).unwrap()).unwrap();
+
+        // every degree's identity is found, wherever it sits in the chain
+        for (username, auth_secret) in path.iter() {
+            let target = identity_commitment(hash, username, auth_secret).unwrap();
+            assert!(is_identity_in_chain(&inputs, target).unwrap());
+        }
+
+        // an outsider is not, nor is a member under a different phrase or secret
+        let outsider = identity_commitment(hash, &String::from("mallory"), &BigInt::from(9));
+        assert!(!is_identity_in_chain(&inputs, outsider.unwrap()).unwrap());
+        let other_hash = phrase_hash(&serialize_phrase(&String::from("other")).unwrap()).unwrap();
+        let other_phrase = identity_commitment(other_hash, &path[1].0, &path[1].1).unwrap();
+        assert!(!is_identity_in_chain(&inputs, other_phrase).unwrap());
+        let other_secret = identity_commitment(hash, &path[1].0, &BigInt::from(99)).unwrap();
+        assert!(!is_identity_in_chain(&inputs, other_secret).unwrap());
+    }
+
+    #[test]
+    fn test_chaff_seed_commitment() {
+        // the prover commits to the seed, then proves with chaff drawn from it
+        let seed = [7u8; 32];
+        let commitment = chaff_seed_commitment(&seed).unwrap();
+        let chaff = CircomPrivateInput::deterministic_chaff(seed, 1);
+        assert!(chaff.is_chaff());
+        let used = CircomWrapper::<Fr>::marshal_private_inputs(&chaff).unwrap();
+
+        // once revealed, an auditor reconstructs exactly the chaff that was used
+        assert_eq!(reconstruct_chaff(commitment, &seed, 1).unwrap(), used);
+
+        // each step draws different chaff and unseeded chaff is not reproducible
+        assert_ne!(reconstruct_chaff(commitment, &seed, 3).unwrap(), used);
+        let unseeded = CircomPrivateInput::empty(true);
+        assert_ne!(
+            CircomWrapper::<Fr>::marshal_private_inputs(&unseeded).unwrap(),
+            CircomWrapper::<Fr>::marshal_private_inputs(&unseeded).unwrap()
+        );
+
+        // a seed that does not open the commitment is rejected
+        assert!(matches!(
+            reconstruct_chaff(commitment, &[8u8; 32], 1),
+            Err(GrapevineError::VerificationFailed(_))
+        ));
+    }
+}
@@ -1,21 +1,136 @@
 use ark_ff::{PrimeField, BigInteger};
 use ark_pallas::Fr;
-use ark_std::rand::rngs::OsRng;
+use ark_std::rand::{rngs::OsRng, Rng};
+use hkdf::Hkdf;
+use sha2::Sha256;
 use std::error::Error;
 use num_bigint::{BigInt, Sign::Plus, RandBigInt};
+use serde::{Deserialize, Serialize};
 
-use super::{MAX_SECRET_LENGTH, MAX_USERNAME_LENGTH, SECRET_FIELD_LENGTH};
+use super::{
+    wrapper::{CircomPrivateInput, CircomWrapper, Phrase},
+    MAX_SECRET_LENGTH, MAX_USERNAME_LENGTH, SECRET_FIELD_LENGTH,
+};
+use crate::errors::GrapevineError;
 
 /** Get the starting ivc inputs (z0) for the grapevine circuit */
 pub fn get_z0<F: PrimeField>() -> [F; 4] {
     (0..4).map(|_| F::zero()).collect::<Vec<F>>().try_into().unwrap()
 }
 
+// returns true if z0 is the all-zero state a fresh (non-resumed) proof starts from, whatever
+// the state width of the circuit (checked against the circuit when folding and verifying)
+pub fn is_fresh_start<F: PrimeField>(z0: &[F]) -> bool {
+    !z0.is_empty() && z0.iter().all(|value| value.is_zero())
+}
+
+/**
+ * Checks that every element of an ivc state is a canonical field element
+ * @dev an element is canonical if re-encoding it from its integer value yields the same representation
+ *
+ * @param z - the ivc state to check
+ * @return - error with the index of the first non-canonical element
+ */
+pub fn validate_state<F: PrimeField>(z: &[F]) -> Result<(), GrapevineError> {
+    for (index, value) in z.iter().enumerate() {
+        if F::from_bigint(value.into_bigint()) != Some(*value) {
+            return Err(GrapevineError::NonCanonicalState { index });
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Checks that state slots the circuit must never write are still zero
+ * @dev run after each step to catch a circuit bug dirtying a reserved slot as soon as it happens
+ *
+ * @param z - the ivc state to check
+ * @param reserved - the indices of the slots that must be zero
+ * @return - error with the index of the first nonzero reserved slot
+ */
+pub fn assert_reserved_slots_zero<F: PrimeField>(
+    z: &[F],
+    reserved: &[usize],
+) -> Result<(), GrapevineError> {
+    for &index in reserved {
+        match z.get(index) {
+            Some(value) if value.is_zero() => {}
+            Some(_) => return Err(GrapevineError::ReservedSlotNonZero { index }),
+            None => {
+                return Err(GrapevineError::InvalidInput(format!(
+                    "Reserved slot {} is outside a state of {} slots",
+                    index,
+                    z.len()
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Converts a step count into a field element without truncation
+ *
+ * @param n - the number of steps folded
+ * @return - n as a field element
+ */
+pub fn step_count_to_fr(n: usize) -> Result<Fr, GrapevineError> {
+    let n = u64::try_from(n).map_err(|_| {
+        GrapevineError::FieldConversion("Step count exceeds the field".to_string())
+    })?;
+    Ok(Fr::from(n))
+}
+
+/**
+ * Converts a bigint into a field element
+ *
+ * @param value - the bigint to convert (must be non-negative and below the modulus)
+ * @return - the field element
+ */
+pub fn bigint_to_f<F: PrimeField>(value: &BigInt) -> Result<F, GrapevineError> {
+    let ark_bigint = CircomWrapper::<F>::num_bigint_to_ark_bigint(value)
+        .map_err(|e| GrapevineError::FieldConversion(e.to_string()))?;
+    F::from_bigint(ark_bigint).ok_or(GrapevineError::FieldConversion(
+        "Value exceeds field modulus".to_string(),
+    ))
+}
+
 /** Generates a random field element for given field as bigint */
 pub fn random_f_bigint<F: PrimeField>() -> BigInt {
+    random_f_bigint_with::<F, _>(&mut OsRng)
+}
+
+// generates a random field element as a bigint from the given rng
+pub fn random_f_bigint_with<F: PrimeField, R: Rng>(rng: &mut R) -> BigInt {
     let lower_bound = BigInt::from(0);
     let upper_bound = BigInt::from_bytes_be(Plus, &F::MODULUS.to_bytes_be());
-    OsRng.gen_bigint_range(&lower_bound, &upper_bound)
+    rng.gen_bigint_range(&lower_bound, &upper_bound)
+}
+
+// HKDF salt separating derived auth secrets from any other use of the master key
+const AUTH_SECRET_SALT: &[u8] = b"grapevine-auth-secret-v1";
+
+/**
+ * Deterministically derives an auth secret from a master key with HKDF-SHA256
+ * @dev 64 bytes are expanded before reducing mod the field prime so the bias is negligible
+ *
+ * @param master_key - the user's backed up master key
+ * @param relationship_id - the label of the relationship the secret is for
+ * @return - the auth secret as a field element
+ */
+pub fn derive_auth_secret<F: PrimeField>(
+    master_key: &[u8],
+    relationship_id: &str,
+) -> Result<BigInt, GrapevineError> {
+    if master_key.is_empty() {
+        return Err(GrapevineError::InvalidInput("Master key is empty".to_string()));
+    }
+    let mut okm = [0u8; 64];
+    Hkdf::<Sha256>::new(Some(AUTH_SECRET_SALT), master_key)
+        .expand(relationship_id.as_bytes(), &mut okm)
+        .map_err(|e| GrapevineError::HashError(e.to_string()))?;
+    let secret = F::from_be_bytes_mod_order(&okm);
+    Ok(CircomWrapper::<F>::ark_primefield_to_num_bigint(secret))
 }
 
 /**
@@ -32,26 +147,122 @@ pub fn serialize_phrase(
     if phrase.len() > MAX_SECRET_LENGTH {
         return Err("Phrase must be <= 180 characters".into());
     }
+    Ok(serialize_phrase_bytes(phrase.as_bytes())?)
+}
+
+/**
+ * Converts arbitrary (not necessarily utf8) bytes to array of 6 field elements
+ * @dev split into 31-byte chunks and pad with 0's, identically to `serialize_phrase`
+ *
+ * @param bytes - the secret bytes (must be <= 180 bytes)
+ * @return - array of 6 bigints
+ */
+pub fn serialize_phrase_bytes(bytes: &[u8]) -> Result<[BigInt; SECRET_FIELD_LENGTH], GrapevineError> {
+    if bytes.len() > MAX_SECRET_LENGTH {
+        return Err(GrapevineError::InvalidInput(
+            "Phrase must be <= 180 bytes".to_string(),
+        ));
+    }
     // convert each 31-byte chunk to field element
     let mut chunks: [BigInt; SECRET_FIELD_LENGTH] = Default::default();
-    for i in 0..SECRET_FIELD_LENGTH {
-        // get the range
-        let start = i * 31;
-        let end = (i + 1) * 31;
-        let mut chunk: [u8; 32] = [0; 32];
-        // select slice from range and pad if needed
-        if start >= phrase.len() {
-        } else if end > phrase.len() {
-            chunk[1..(phrase.len() - start + 1)].copy_from_slice(&phrase.as_bytes()[start..]);
-        } else {
-            chunk[1..32].copy_from_slice(&phrase.as_bytes()[start..end]);
+    for (i, chunk) in chunks.iter_mut().enumerate() {
+        // select slice from range, empty if past the end of the phrase
+        let start = (i * 31).min(bytes.len());
+        let end = ((i + 1) * 31).min(bytes.len());
+        *chunk = pack_bytes_to_field(&bytes[start..end])?;
+    }
+    Ok(chunks)
+}
+
+/**
+ * Recovers the bytes serialized with `serialize_phrase_bytes`
+ * @dev the zero padding is indistinguishable from trailing zero bytes so the length is required
+ *
+ * @param chunks - the 6 serialized chunks
+ * @param len - the number of bytes serialized
+ * @return - the original bytes
+ */
+pub fn deserialize_phrase_bytes(
+    chunks: &[BigInt; SECRET_FIELD_LENGTH],
+    len: usize,
+) -> Result<Vec<u8>, GrapevineError> {
+    if len > MAX_SECRET_LENGTH {
+        return Err(GrapevineError::InvalidInput(
+            "Phrase must be <= 180 bytes".to_string(),
+        ));
+    }
+    let mut bytes = unpack_chunks(chunks)?;
+    bytes.truncate(len);
+    Ok(bytes)
+}
+
+// unpacks 31-byte chunks packed with `pack_bytes_to_field` back into contiguous bytes
+fn unpack_chunks(chunks: &[BigInt; SECRET_FIELD_LENGTH]) -> Result<Vec<u8>, GrapevineError> {
+    let mut bytes = Vec::with_capacity(SECRET_FIELD_LENGTH * 31);
+    for chunk in chunks.iter() {
+        let (_, chunk_bytes) = chunk.to_bytes_be();
+        if chunk_bytes.len() > 31 {
+            return Err(GrapevineError::InvalidInput(
+                "Chunk exceeds 31 bytes".to_string(),
+            ));
         }
-        // wrap in field element
-        chunks[i] = BigInt::from_bytes_be(Plus, &chunk);
+        // restore leading zero bytes dropped by the bigint encoding
+        bytes.extend(std::iter::repeat(0u8).take(31 - chunk_bytes.len()));
+        bytes.extend(chunk_bytes);
+    }
+    Ok(bytes)
+}
+
+/**
+ * Converts a given phrase to 6 field elements without leaking its length through chunk occupancy
+ * @dev encodes [length byte || phrase || random padding] across all 6 chunks of 31 bytes
+ *
+ * @param phrase - the phrase to serialize (must be <= 180 bytes)
+ * @param rng - the source of the random padding
+ * @return - array of 6 bigints, all filled regardless of phrase length
+ */
+pub fn serialize_phrase_length_hiding<R: Rng>(
+    phrase: &str,
+    rng: &mut R,
+) -> Result<[BigInt; SECRET_FIELD_LENGTH], GrapevineError> {
+    if phrase.len() > MAX_SECRET_LENGTH {
+        return Err(GrapevineError::InvalidInput(
+            "Phrase must be <= 180 characters".to_string(),
+        ));
+    }
+    let mut bytes = [0u8; SECRET_FIELD_LENGTH * 31];
+    rng.fill_bytes(&mut bytes);
+    bytes[0] = phrase.len() as u8;
+    bytes[1..(phrase.len() + 1)].copy_from_slice(phrase.as_bytes());
+
+    let mut chunks: [BigInt; SECRET_FIELD_LENGTH] = Default::default();
+    for (chunk, packed) in chunks.iter_mut().zip(bytes.chunks(31)) {
+        *chunk = pack_bytes_to_field(packed)?;
     }
     Ok(chunks)
 }
 
+/**
+ * Recovers a phrase serialized with `serialize_phrase_length_hiding`
+ * @dev reads the length prefix and discards the random padding
+ *
+ * @param chunks - the 6 serialized chunks
+ * @return - the original phrase
+ */
+pub fn deserialize_phrase_length_hiding(
+    chunks: &[BigInt; SECRET_FIELD_LENGTH],
+) -> Result<String, GrapevineError> {
+    let bytes = unpack_chunks(chunks)?;
+    let len = bytes[0] as usize;
+    if len > MAX_SECRET_LENGTH {
+        return Err(GrapevineError::InvalidInput(
+            "Length prefix exceeds 180 characters".to_string(),
+        ));
+    }
+    String::from_utf8(bytes[1..(len + 1)].to_vec())
+        .map_err(|e| GrapevineError::InvalidInput(e.to_string()))
+}
+
 /**
 * Converts a given username to a field element
 *
@@ -63,9 +274,715 @@ pub fn serialize_username(username: &String) -> Result<BigInt, Box<dyn Error>> {
     if username.len() > MAX_USERNAME_LENGTH {
         return Err("Username must be <= 30 characters".into());
     }
-    // convert to big endian bytes
-    let mut bytes: [u8; 32] = [0; 32];
-    bytes[1..(username.len() + 1)].copy_from_slice(&username.as_bytes()[..]);
-    // convert to bigint
-    Ok(BigInt::from_bytes_be(Plus, &bytes))
+    Ok(pack_bytes_to_field(username.as_bytes())?)
+}
+
+// Characters a deployment allows in usernames
+#[derive(Clone, Copy, Debug)]
+pub enum CharsetPolicy {
+    // ascii letters and digits only (no spaces or lookalike unicode)
+    AsciiAlphanumeric,
+    // a caller supplied predicate over each character
+    Custom(fn(char) -> bool),
+}
+
+impl CharsetPolicy {
+    // returns true if the policy allows the character
+    pub fn allows(&self, ch: char) -> bool {
+        match self {
+            CharsetPolicy::AsciiAlphanumeric => ch.is_ascii_alphanumeric(),
+            CharsetPolicy::Custom(allowed) => allowed(ch),
+        }
+    }
+}
+
+/**
+ * Checks every character of a username against a charset policy
+ * @dev an application level guard against homograph handles, run before serialization
+ *
+ * @param username - the username to check
+ * @param policy - the characters allowed in usernames
+ * @return - error with the first disallowed character
+ */
+pub fn validate_username_charset(
+    username: &str,
+    policy: CharsetPolicy,
+) -> Result<(), GrapevineError> {
+    match username.chars().find(|ch| !policy.allows(*ch)) {
+        Some(ch) => Err(GrapevineError::InvalidUsernameChar { ch }),
+        None => Ok(()),
+    }
+}
+
+/**
+ * Packs up to 31 bytes into a big endian 32-byte word and returns it as a bigint
+ * @dev content is placed at bytes[1..] and right padded with 0's. bytes[0] is reserved as a
+ *      zero high byte so the value is always below the field modulus
+ *
+ * @param bytes - the bytes to pack (must be < 32 bytes)
+ * @return - the packed bytes as a bigint
+ */
+pub fn pack_bytes_to_field(bytes: &[u8]) -> Result<BigInt, GrapevineError> {
+    if bytes.len() >= 32 {
+        return Err(GrapevineError::InvalidInput(format!(
+            "Cannot pack {} bytes into a field element (max 31)",
+            bytes.len()
+        )));
+    }
+    let mut word: [u8; 32] = [0; 32];
+    word[1..(bytes.len() + 1)].copy_from_slice(bytes);
+    Ok(BigInt::from_bytes_be(Plus, &word))
+}
+
+/**
+ * Rebuilds the interleaved (logic, chaff) input sequence for a chain from the prover's secrets
+ * @dev degree 1 (the phrase holder revealing the phrase) is the smallest chain, an empty path
+ *      proves nothing and is rejected with DegreeTooLow
+ *
+ * @param phrase - the secret phrase at the origin of the chain
+ * @param path - the (username, auth secret) of each degree in order
+ * @return - the step inputs that fold the chain
+ */
+pub fn reconstruct_inputs(
+    phrase: &String,
+    path: &[(String, BigInt)],
+) -> Result<Vec<CircomPrivateInput>, GrapevineError> {
+    if path.is_empty() {
+        return Err(GrapevineError::DegreeTooLow);
+    }
+    let mut inputs = Vec::with_capacity(path.len() * 2);
+    for (i, (username, auth_secret)) in path.iter().enumerate() {
+        let logic = match i {
+            0 => CircomPrivateInput::new(
+                Some(phrase.clone()),
+                [None, Some(username.clone())],
+                [None, Some(auth_secret.clone())],
+                false,
+            )?,
+            _ => {
+                let (prev_username, prev_auth_secret) = &path[i - 1];
+                CircomPrivateInput::new(
+                    None,
+                    [Some(prev_username.clone()), Some(username.clone())],
+                    [Some(prev_auth_secret.clone()), Some(auth_secret.clone())],
+                    false,
+                )?
+            }
+        };
+        inputs.push(logic);
+        inputs.push(CircomPrivateInput::empty(true));
+    }
+    Ok(inputs)
+}
+
+/**
+ * Pre-flight check that an interleaved input sequence forms a well-formed chain
+ * @dev checks logic and chaff steps alternate, the first logic step reveals the phrase, and
+ *      each later logic step shifts the prior degree's identity into slot 0. The chain may end
+ *      with chaff padding steps but must fold an even number of steps, as `verify` requires
+ *
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @return - error with the index and reason of the first violation
+ */
+pub fn validate_input_sequence(inputs: &[CircomPrivateInput]) -> Result<(), GrapevineError> {
+    let violation = |index: usize, reason: &str| GrapevineError::InvalidSequence {
+        index,
+        reason: reason.to_string(),
+    };
+    let mut prev_logic: Option<&CircomPrivateInput> = None;
+    for (index, input) in inputs.iter().enumerate() {
+        // odd steps are chaff
+        if index % 2 == 1 {
+            if !input.is_chaff() {
+                return Err(violation(index, "expected a chaff step"));
+            }
+            continue;
+        }
+        if input.is_chaff() {
+            // a trailing run of chaff is padding (see `pad_chain_to`)
+            if prev_logic.is_some() && inputs[index..].iter().all(|i| i.is_chaff()) {
+                break;
+            }
+            return Err(violation(index, "expected a logic step"));
+        }
+        let [prev_username, username] = input.usernames();
+        let [prev_auth_secret, auth_secret] = input.auth_secrets();
+        if username.is_none() || auth_secret.is_none() {
+            return Err(violation(index, "missing current username or auth secret"));
+        }
+        match prev_logic {
+            None => {
+                if input.phrase().is_none() {
+                    return Err(violation(index, "first logic step must carry the phrase"));
+                }
+                if prev_username.is_some() || prev_auth_secret.is_some() {
+                    return Err(violation(index, "first logic step has no previous identity"));
+                }
+            }
+            Some(prev) => {
+                if input.phrase().is_some() {
+                    return Err(violation(index, "only the first logic step carries the phrase"));
+                }
+                if prev_username != &prev.usernames()[1]
+                    || prev_auth_secret != &prev.auth_secrets()[1]
+                {
+                    return Err(violation(index, "previous identity does not match prior degree"));
+                }
+            }
+        }
+        prev_logic = Some(input);
+    }
+    if inputs.len() % 2 == 1 {
+        return Err(GrapevineError::OddStepCount(inputs.len()));
+    }
+    Ok(())
+}
+
+/**
+ * Pads a chain with chaff steps so every chain up to a given degree folds the same number of steps
+ * @dev hides the degree from anyone who can only observe the step count
+ *
+ * @param inputs - the interleaved (logic, chaff) step inputs
+ * @param target_steps - the number of steps the padded chain should have (must be even)
+ * @return - the inputs followed by chaff steps up to target_steps
+ */
+pub fn pad_chain_to(
+    mut inputs: Vec<CircomPrivateInput>,
+    target_steps: usize,
+) -> Result<Vec<CircomPrivateInput>, GrapevineError> {
+    if target_steps % 2 != 0 {
+        return Err(GrapevineError::OddStepCount(target_steps));
+    }
+    if inputs.len() > target_steps {
+        return Err(GrapevineError::InvalidInput(format!(
+            "Chain of {} steps exceeds the target of {}",
+            inputs.len(),
+            target_steps
+        )));
+    }
+    inputs.resize(target_steps, CircomPrivateInput::padding());
+    Ok(inputs)
+}
+
+// JSON form of a step input, with auth secrets as decimal strings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct StepInputJson {
+    phrase: Option<String>,
+    // binary phrase, mutually exclusive with phrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    phrase_bytes: Option<Vec<u8>>,
+    usernames: [Option<String>; 2],
+    auth_secrets: [Option<String>; 2],
+    chaff: bool,
+    // chaff padding past the last degree, which may fold at logic steps
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    padding: bool,
+}
+
+/**
+ * Renders a step input sequence as a JSON array
+ *
+ * @param inputs - the step inputs
+ * @return - an array of { phrase, usernames, auth_secrets, chaff } objects
+ */
+pub fn inputs_to_json(inputs: &[CircomPrivateInput]) -> serde_json::Value {
+    let steps = inputs
+        .iter()
+        .map(|input| StepInputJson {
+            phrase: match input.phrase() {
+                Some(Phrase::Text(text)) => Some(text.clone()),
+                _ => None,
+            },
+            phrase_bytes: match input.phrase() {
+                Some(Phrase::Bytes(bytes)) => Some(bytes.clone()),
+                _ => None,
+            },
+            usernames: input.usernames().clone(),
+            auth_secrets: input.auth_secrets().clone().map(|a| a.map(|a| a.to_string())),
+            chaff: input.is_chaff(),
+            padding: input.is_padding(),
+        })
+        .collect::<Vec<StepInputJson>>();
+    serde_json::to_value(steps).unwrap()
+}
+
+/**
+ * Parses a step input sequence from a JSON array
+ *
+ * @param doc - an array of { phrase, usernames, auth_secrets, chaff } objects
+ * @return - the step inputs, or an error naming the first malformed step
+ */
+pub fn inputs_from_json(doc: &serde_json::Value) -> Result<Vec<CircomPrivateInput>, GrapevineError> {
+    let steps = doc.as_array().ok_or(GrapevineError::InvalidInput(
+        "Chain document must be an array of steps".to_string(),
+    ))?;
+    steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            let violation = |reason: String| GrapevineError::InvalidSequence { index, reason };
+            let step = StepInputJson::deserialize(step).map_err(|e| violation(e.to_string()))?;
+            let mut auth_secrets: [Option<BigInt>; 2] = [None, None];
+            for (secret, parsed) in step.auth_secrets.iter().zip(auth_secrets.iter_mut()) {
+                if let Some(secret) = secret {
+                    let value = secret
+                        .parse::<BigInt>()
+                        .map_err(|e| violation(format!("invalid auth secret: {}", e)))?;
+                    *parsed = Some(value);
+                }
+            }
+            if step.phrase.is_some() && step.phrase_bytes.is_some() {
+                return Err(violation("phrase and phrase_bytes are both set".to_string()));
+            }
+            let mut input =
+                CircomPrivateInput::new(step.phrase, step.usernames, auth_secrets, step.chaff)
+                    .map_err(|e| violation(e.to_string()))?;
+            if step.padding {
+                if !input.is_chaff() {
+                    return Err(violation("padding steps must be chaff".to_string()));
+                }
+                input = CircomPrivateInput::padding();
+            }
+            match step.phrase_bytes {
+                Some(bytes) => input.with_phrase_bytes(bytes).map_err(|e| violation(e.to_string())),
+                None => Ok(input),
+            }
+        })
+        .collect()
+}
+
+/**
+ * Canonicalizes and validates a submitted chain document, ready to fold
+ * @dev the single entry point for a chain received over the wire. The phrase and usernames are
+ *      canonicalized by trimming surrounding whitespace before parsing, so the identities a
+ *      step shifts into its previous slot compare equal however they were typed
+ *
+ * @param doc - an array of { phrase, usernames, auth_secrets, chaff } objects
+ * @return - the step inputs, or InvalidSequence naming the first offending step
+ */
+pub fn validate_json_chain(
+    doc: &serde_json::Value,
+) -> Result<Vec<CircomPrivateInput>, GrapevineError> {
+    let mut canonical = doc.clone();
+    if let Some(steps) = canonical.as_array_mut() {
+        for step in steps.iter_mut() {
+            if let Some(phrase) = step.get_mut("phrase") {
+                if let Some(trimmed) = phrase.as_str().map(|p| p.trim().to_string()) {
+                    *phrase = trimmed.into();
+                }
+            }
+            if let Some(usernames) = step.get_mut("usernames").and_then(|u| u.as_array_mut()) {
+                for username in usernames.iter_mut() {
+                    if let Some(trimmed) = username.as_str().map(|u| u.trim().to_string()) {
+                        *username = trimmed.into();
+                    }
+                }
+            }
+        }
+    }
+    let inputs = inputs_from_json(&canonical)?;
+    for (index, input) in inputs.iter().enumerate() {
+        for username in input.usernames().iter().flatten() {
+            serialize_username(username).map_err(|e| GrapevineError::InvalidSequence {
+                index,
+                reason: e.to_string(),
+            })?;
+        }
+    }
+    validate_input_sequence(&inputs)?;
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circom::GrapevineFCircuit;
+    use crate::nova::prove_chain;
+    use crate::params::test_nova_setup;
+    use crate::proof::verify;
+    use crate::test_utils::{sample_chain, sample_circuit};
+    use crate::utils::{CHAFF_INDEX, DEGREE_INDEX};
+
+    #[test]
+    fn test_validate_state() {
+        // fresh state is canonical
+        assert!(validate_state(&get_z0::<Fr>()).is_ok());
+
+        // construct an element whose internal representation is the modulus itself
+        let non_canonical = Fr::new_unchecked(Fr::MODULUS);
+        let z = vec![Fr::from(1), Fr::from(2), non_canonical, Fr::from(0)];
+        assert!(matches!(
+            validate_state(&z),
+            Err(GrapevineError::NonCanonicalState { index: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_assert_reserved_slots_zero() {
+        let mut z = get_z0::<Fr>().to_vec();
+        z[DEGREE_INDEX] = Fr::from(2);
+        assert!(assert_reserved_slots_zero(&z, &[CHAFF_INDEX]).is_ok());
+        assert!(matches!(
+            assert_reserved_slots_zero(&z, &[CHAFF_INDEX, DEGREE_INDEX]),
+            Err(GrapevineError::ReservedSlotNonZero { index: DEGREE_INDEX })
+        ));
+        assert!(matches!(
+            assert_reserved_slots_zero(&z, &[z.len()]),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_fresh_start() {
+        assert!(is_fresh_start(&get_z0::<Fr>()));
+        assert!(!is_fresh_start(&[Fr::from(0), Fr::from(1), Fr::from(0), Fr::from(0)]));
+        // the width follows the circuit, but an empty state starts nothing
+        assert!(is_fresh_start(&[Fr::from(0); 6]));
+        assert!(!is_fresh_start::<Fr>(&[]));
+    }
+
+    #[test]
+    fn test_step_count_to_fr() {
+        assert_eq!(step_count_to_fr(10).unwrap(), Fr::from(10u32));
+
+        // counts above u32::MAX are not truncated
+        let n = u32::MAX as usize + 1;
+        assert_eq!(step_count_to_fr(n).unwrap(), Fr::from(1u64 << 32));
+        assert_ne!(step_count_to_fr(n).unwrap(), Fr::from(n as u32));
+    }
+
+    #[test]
+    fn test_derive_auth_secret() {
+        let master_key = b"master key backed up by the user";
+        let secret = derive_auth_secret::<Fr>(master_key, "alice->bob").unwrap();
+
+        // deterministic, and distinct per relationship and per master key
+        assert_eq!(derive_auth_secret::<Fr>(master_key, "alice->bob").unwrap(), secret);
+        assert_ne!(derive_auth_secret::<Fr>(master_key, "alice->carol").unwrap(), secret);
+        assert_ne!(derive_auth_secret::<Fr>(b"another master key", "alice->bob").unwrap(), secret);
+
+        // always a valid field element
+        assert!(bigint_to_f::<Fr>(&secret).is_ok());
+        assert!(derive_auth_secret::<Fr>(&[], "alice->bob").is_err());
+    }
+
+    #[test]
+    fn test_pack_bytes_to_field() {
+        // 30 bytes are right padded below the reserved high byte
+        let packed = pack_bytes_to_field(&[0xff; 30]).unwrap();
+        let mut expected = [0u8; 32];
+        expected[1..31].copy_from_slice(&[0xff; 30]);
+        assert_eq!(packed, BigInt::from_bytes_be(Plus, &expected));
+
+        // 31 bytes fill everything but the high byte and stay below the modulus
+        let packed = pack_bytes_to_field(&[0xff; 31]).unwrap();
+        assert_eq!(packed.to_bytes_be().1, vec![0xff; 31]);
+        assert!(bigint_to_f::<Fr>(&packed).is_ok());
+
+        // 32 bytes would need the reserved high byte
+        assert!(matches!(
+            pack_bytes_to_field(&[0xff; 32]),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_username_charset() {
+        // ascii alphanumeric rejects spaces and lookalike unicode
+        let ascii = CharsetPolicy::AsciiAlphanumeric;
+        assert!(validate_username_charset("alice42", ascii).is_ok());
+        assert!(matches!(
+            validate_username_charset("alice 42", ascii),
+            Err(GrapevineError::InvalidUsernameChar { ch: ' ' })
+        ));
+        assert!(matches!(
+            validate_username_charset("\u{0430}lice", ascii),
+            Err(GrapevineError::InvalidUsernameChar { ch: '\u{0430}' })
+        ));
+
+        // a custom policy reports the first character it disallows
+        let lowercase = CharsetPolicy::Custom(|ch| ch.is_ascii_lowercase() || ch == '_');
+        assert!(validate_username_charset("alice_b", lowercase).is_ok());
+        assert!(matches!(
+            validate_username_charset("aliCE", lowercase),
+            Err(GrapevineError::InvalidUsernameChar { ch: 'C' })
+        ));
+    }
+
+    #[test]
+    fn test_serialize_phrase_length_hiding() {
+        let mut rng = ark_std::test_rng();
+        let short = String::from("short");
+        let long = String::from("a considerably longer phrase that spans several chunks");
+
+        // round trip through the random padding
+        let short_chunks = serialize_phrase_length_hiding(&short, &mut rng).unwrap();
+        let long_chunks = serialize_phrase_length_hiding(&long, &mut rng).unwrap();
+        assert_eq!(deserialize_phrase_length_hiding(&short_chunks).unwrap(), short);
+        assert_eq!(deserialize_phrase_length_hiding(&long_chunks).unwrap(), long);
+
+        // zero padding leaks the length, length hiding fills every chunk
+        let occupancy = |chunks: &[BigInt]| -> Vec<bool> {
+            chunks.iter().map(|c| *c != BigInt::from(0)).collect()
+        };
+        assert_ne!(
+            occupancy(&serialize_phrase(&short).unwrap()),
+            occupancy(&serialize_phrase(&long).unwrap())
+        );
+        assert_eq!(occupancy(&short_chunks), vec![true; SECRET_FIELD_LENGTH]);
+        assert_eq!(occupancy(&short_chunks), occupancy(&long_chunks));
+    }
+
+    #[test]
+    fn test_serialize_phrase_bytes() {
+        // 180 random bytes fill every chunk
+        let mut rng = ark_std::test_rng();
+        let mut bytes = [0u8; MAX_SECRET_LENGTH];
+        rng.fill(&mut bytes[..]);
+        let chunks = serialize_phrase_bytes(&bytes).unwrap();
+        assert_eq!(deserialize_phrase_bytes(&chunks, bytes.len()).unwrap(), bytes.to_vec());
+        assert!(chunks.iter().all(|chunk| bigint_to_f::<Fr>(chunk).is_ok()));
+
+        // utf8 phrases serialize the same as their bytes
+        let phrase = String::from("This is a secret");
+        assert_eq!(
+            serialize_phrase(&phrase).unwrap(),
+            serialize_phrase_bytes(phrase.as_bytes()).unwrap()
+        );
+
+        // one byte too many
+        assert!(serialize_phrase_bytes(&[0u8; MAX_SECRET_LENGTH + 1]).is_err());
+
+        // a binary phrase round trips through the json input format
+        let input = CircomPrivateInput::new(
+            None,
+            [None, Some(String::from("alice"))],
+            [None, Some(BigInt::from(1))],
+            false,
+        )
+        .unwrap()
+        .with_phrase_bytes(bytes.to_vec())
+        .unwrap();
+        let parsed = inputs_from_json(&inputs_to_json(&[input])).unwrap();
+        assert_eq!(parsed[0].phrase(), Some(&Phrase::Bytes(bytes.to_vec())));
+    }
+
+    #[test]
+    fn test_phrase_length_boundary() {
+        // a 180 byte phrase with no zero bytes, so a lost byte cannot hide in the padding
+        let phrase = (0..MAX_SECRET_LENGTH)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect::<String>();
+        let chunks = serialize_phrase(&phrase).unwrap();
+
+        // the first five chunks are full and the last holds the remaining 25 bytes
+        let unpacked = unpack_chunks(&chunks).unwrap();
+        assert_eq!(unpacked.len(), SECRET_FIELD_LENGTH * 31);
+        assert_eq!(&unpacked[..MAX_SECRET_LENGTH], phrase.as_bytes());
+        assert!(unpacked[MAX_SECRET_LENGTH..].iter().all(|byte| *byte == 0));
+        assert_eq!(
+            deserialize_phrase_bytes(&chunks, MAX_SECRET_LENGTH).unwrap(),
+            phrase.as_bytes().to_vec()
+        );
+
+        // 181 bytes is rejected rather than truncated
+        let too_long = format!("{}z", phrase);
+        assert!(serialize_phrase(&too_long).is_err());
+        assert!(serialize_phrase_bytes(too_long.as_bytes()).is_err());
+        assert!(CircomPrivateInput::new(
+            Some(too_long),
+            [None, Some(String::from("alice"))],
+            [None, Some(BigInt::from(1))],
+            false,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_pad_chain_to() {
+        let f_circuit = sample_circuit();
+        let (prover_params, verifier_params) =
+            test_nova_setup::<GrapevineFCircuit<Fr>>(f_circuit.clone());
+
+        // pad a degree 2 chain to 10 steps
+        let (inputs, _) = sample_chain(2);
+        let padded = pad_chain_to(inputs.clone(), 10).unwrap();
+        assert_eq!(padded.len(), 10);
+        assert!(padded[4..].iter().all(|input| input.is_chaff()));
+        assert!(validate_input_sequence(&padded).is_ok());
+
+        // the padded chain folds and verifies
+        let proof = prove_chain(&prover_params, f_circuit, &padded).unwrap();
+        assert_eq!(proof.num_steps, 10);
+        verify(&verifier_params, &proof).unwrap();
+
+        // a chain longer than the target cannot be padded
+        assert!(matches!(
+            pad_chain_to(inputs.clone(), 2),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+
+        // padding to an odd step count would split a (logic, chaff) pair
+        assert!(matches!(
+            pad_chain_to(inputs, 9),
+            Err(GrapevineError::OddStepCount(9))
+        ));
+    }
+
+    #[test]
+    fn test_inputs_json() {
+        let path = vec![
+            (String::from("alice"), BigInt::from(1)),
+            (String::from("bob"), BigInt::from(2)),
+        ];
+        let inputs = reconstruct_inputs(&String::from("This is a secret"), &path).unwrap();
+
+        // round trip through json
+        let doc = inputs_to_json(&inputs);
+        assert_eq!(doc[1]["chaff"], serde_json::json!(true));
+        assert_eq!(doc[2]["auth_secrets"][1], serde_json::json!("2"));
+        let parsed = inputs_from_json(&doc).unwrap();
+        assert_eq!(inputs_to_json(&parsed), doc);
+
+        // padding survives the round trip
+        let padded = inputs_from_json(&inputs_to_json(&pad_chain_to(inputs, 6).unwrap())).unwrap();
+        assert!(padded[4].is_padding() && !padded[3].is_padding());
+
+        // a chaff step carrying fields is rejected at its index
+        let mut malformed = doc.clone();
+        malformed[1]["phrase"] = serde_json::json!("leak");
+        assert!(matches!(
+            inputs_from_json(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_json_chain() {
+        let path = vec![
+            (String::from("alice"), BigInt::from(1)),
+            (String::from("bob"), BigInt::from(2)),
+        ];
+        let inputs = reconstruct_inputs(&String::from("This is a secret"), &path).unwrap();
+        let doc = inputs_to_json(&inputs);
+        let parsed = validate_json_chain(&doc).unwrap();
+        assert_eq!(inputs_to_json(&parsed), doc);
+
+        // surrounding whitespace is trimmed, so a sloppily typed chain still links up
+        let mut sloppy = doc.clone();
+        sloppy[0]["phrase"] = serde_json::json!(" This is a secret\n");
+        sloppy[0]["usernames"][1] = serde_json::json!("alice ");
+        sloppy[2]["usernames"][0] = serde_json::json!("  alice");
+        assert_eq!(inputs_to_json(&validate_json_chain(&sloppy).unwrap()), doc);
+
+        // the document must be an array
+        assert!(matches!(
+            validate_json_chain(&serde_json::json!({ "steps": [] })),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+
+        // wrong types
+        let mut malformed = doc.clone();
+        malformed[2]["chaff"] = serde_json::json!("no");
+        assert!(matches!(
+            validate_json_chain(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 2, .. })
+        ));
+        let mut malformed = doc.clone();
+        malformed[2]["auth_secrets"][1] = serde_json::json!("two");
+        assert!(matches!(
+            validate_json_chain(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 2, .. })
+        ));
+
+        // bad sequence
+        let mut malformed = doc.clone();
+        malformed[2]["usernames"][0] = serde_json::json!("mallory");
+        assert!(matches!(
+            validate_json_chain(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 2, .. })
+        ));
+
+        // overlong phrase and username
+        let mut malformed = doc.clone();
+        malformed[0]["phrase"] = serde_json::json!("a".repeat(MAX_SECRET_LENGTH + 1));
+        assert!(matches!(
+            validate_json_chain(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 0, .. })
+        ));
+        let mut malformed = doc.clone();
+        malformed[2]["usernames"][1] = serde_json::json!("b".repeat(MAX_USERNAME_LENGTH + 1));
+        assert!(matches!(
+            validate_json_chain(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_input_sequence() {
+        let path = vec![
+            (String::from("alice"), BigInt::from(1)),
+            (String::from("bob"), BigInt::from(2)),
+            (String::from("charlie"), BigInt::from(3)),
+        ];
+        let phrase = String::from("This is a secret");
+        let inputs = reconstruct_inputs(&phrase, &path).unwrap();
+        assert!(validate_input_sequence(&inputs).is_ok());
+
+        // missing chaff step between degrees
+        let mut malformed = inputs.clone();
+        malformed.remove(1);
+        assert!(matches!(
+            validate_input_sequence(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 1, .. })
+        ));
+
+        // first logic step without the phrase
+        let mut malformed = inputs.clone();
+        malformed[0] = CircomPrivateInput::new(
+            None,
+            [None, Some(path[0].0.clone())],
+            [None, Some(path[0].1.clone())],
+            false,
+        )
+        .unwrap();
+        assert!(matches!(
+            validate_input_sequence(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 0, .. })
+        ));
+
+        // degree 3 does not shift degree 2's identity into slot 0
+        let mut malformed = inputs.clone();
+        malformed[4] = CircomPrivateInput::new(
+            None,
+            [Some(path[0].0.clone()), Some(path[2].0.clone())],
+            [Some(path[0].1.clone()), Some(path[2].1.clone())],
+            false,
+        )
+        .unwrap();
+        assert!(matches!(
+            validate_input_sequence(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 4, .. })
+        ));
+
+        // chaff in a logic position
+        let mut malformed = inputs.clone();
+        malformed[2] = CircomPrivateInput::empty(true);
+        assert!(matches!(
+            validate_input_sequence(&malformed),
+            Err(GrapevineError::InvalidSequence { index: 2, .. })
+        ));
+
+        // a well-formed but odd length chain would fold into a proof that does not verify
+        assert!(matches!(
+            validate_input_sequence(&inputs[..1]),
+            Err(GrapevineError::OddStepCount(1))
+        ));
+        let mut odd = inputs.clone();
+        odd.push(CircomPrivateInput::padding());
+        assert!(matches!(
+            validate_input_sequence(&odd),
+            Err(GrapevineError::OddStepCount(7))
+        ));
+    }
 }
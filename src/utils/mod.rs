@@ -4,11 +4,24 @@ use ark_std::rand::rngs::OsRng;
 
 pub mod wrapper;
 pub mod inputs;
+pub mod hash;
+#[cfg(all(feature = "async", feature = "prover"))]
+pub mod pool;
 
 pub const SECRET_FIELD_LENGTH: usize = 6;
 pub const MAX_SECRET_LENGTH: usize = 180;
 pub const MAX_USERNAME_LENGTH: usize = 30;
 
+// a phrase is packed 31 bytes per chunk (a leading zero byte keeps each chunk below the modulus)
+// so the longest phrase must fit in SECRET_FIELD_LENGTH chunks: 180 <= 6 * 31
+const _: () = assert!(MAX_SECRET_LENGTH <= SECRET_FIELD_LENGTH * 31);
+
+// ivc state layout (see grapevine.circom in_out schema)
+pub const DEGREE_INDEX: usize = 0;
+pub const PHRASE_HASH_INDEX: usize = 1;
+pub const SECRET_HASH_INDEX: usize = 2;
+pub const CHAFF_INDEX: usize = 3;
+
 /** Get a random field element */
 pub fn random_fr() -> ark_pallas::Fr {
     Fr::rand(&mut OsRng)
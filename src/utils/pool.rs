@@ -0,0 +1,173 @@
+use ark_ff::PrimeField;
+use num_bigint::BigInt;
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread::JoinHandle,
+};
+use tokio::sync::oneshot;
+
+use super::wrapper::CircomWrapper;
+use crate::errors::GrapevineError;
+
+// A witness job: the marshalled circom inputs and where to send the witness
+struct WitnessJob<F: PrimeField> {
+    inputs: Vec<(String, Vec<BigInt>)>,
+    result: oneshot::Sender<Result<Vec<F>, GrapevineError>>,
+}
+
+// Fixed set of worker threads, each owning a pre-instantiated witness calculator
+pub struct WitnessWorkerPool<F: PrimeField> {
+    jobs: Option<mpsc::Sender<WitnessJob<F>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<F: PrimeField> WitnessWorkerPool<F> {
+    /**
+     * Spawns the workers, instantiating each one's witness calculator up front
+     * @dev at most `size` witnesses are calculated at once; further jobs queue
+     *
+     * @param wrapper - the circom wrapper whose wasm the workers calculate witnesses with
+     * @param size - the number of workers
+     * @return - the pool, or an error if a calculator could not be instantiated
+     */
+    pub fn new(wrapper: CircomWrapper<F>, size: usize) -> Result<Self, GrapevineError> {
+        if size == 0 {
+            return Err(GrapevineError::InvalidInput(
+                "Worker pool needs at least one worker".to_string(),
+            ));
+        }
+        let (jobs, receiver) = mpsc::channel::<WitnessJob<F>>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let workers = (0..size)
+            .map(|_| {
+                let wrapper = wrapper.clone();
+                let receiver = receiver.clone();
+                let ready = ready_tx.clone();
+                std::thread::spawn(move || {
+                    let mut calculator = match wrapper.witness_calculator() {
+                        Ok(calculator) => {
+                            let _ = ready.send(Ok(()));
+                            calculator
+                        }
+                        Err(e) => {
+                            let _ = ready.send(Err(GrapevineError::ArtifactError(e.to_string())));
+                            return;
+                        }
+                    };
+                    // the lock is released as soon as a job is taken off the queue
+                    loop {
+                        let job = match receiver.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            Err(_) => return,
+                        };
+                        let witness = wrapper
+                            .calculate_witness_with(&mut calculator, &job.inputs)
+                            .and_then(|witness| CircomWrapper::<F>::witness_to_field(&witness))
+                            .map_err(|e| GrapevineError::ArtifactError(e.to_string()));
+                        // a dropped receiver only means the caller stopped waiting
+                        let _ = job.result.send(witness);
+                    }
+                })
+            })
+            .collect::<Vec<JoinHandle<()>>>();
+
+        // surface the first worker that failed to instantiate its calculator
+        let pool = Self {
+            jobs: Some(jobs),
+            workers,
+        };
+        for _ in 0..size {
+            ready_rx.recv().map_err(|e| GrapevineError::ArtifactError(e.to_string()))??;
+        }
+        Ok(pool)
+    }
+
+    // returns the number of workers in the pool
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /**
+     * Queues a witness calculation on the next free worker
+     *
+     * @param inputs - the marshalled circom inputs
+     * @return - the witness as field elements
+     */
+    pub async fn calculate(
+        &self,
+        inputs: Vec<(String, Vec<BigInt>)>,
+    ) -> Result<Vec<F>, GrapevineError> {
+        let (result, witness) = oneshot::channel();
+        let shut_down =
+            || GrapevineError::ArtifactError("Witness worker pool shut down".to_string());
+        self.jobs
+            .as_ref()
+            .ok_or_else(shut_down)?
+            .send(WitnessJob { inputs, result })
+            .map_err(|_| shut_down())?;
+        witness.await.map_err(|_| shut_down())?
+    }
+}
+
+impl<F: PrimeField> Drop for WitnessWorkerPool<F> {
+    // closes the queue and waits for in-flight jobs to finish
+    fn drop(&mut self) {
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::wrapper::CircomPrivateInput;
+    use ark_pallas::Fr;
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn test_witness_worker_pool() {
+        let wrapper = CircomWrapper::<Fr>::new(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        );
+        let pool = WitnessWorkerPool::new(wrapper.clone(), 2).unwrap();
+        assert_eq!(pool.size(), 2);
+
+        // queue more jobs than there are workers
+        let jobs = (0..6u64)
+            .map(|i| {
+                let input = CircomPrivateInput::new(
+                    Some(String::from("This is a secret")),
+                    [None, Some(format!("user{}", i))],
+                    [None, Some(BigInt::from(i + 1))],
+                    false,
+                )
+                .unwrap();
+                let mut inputs = vec![("ivc_input".to_string(), vec![BigInt::from(i); 4])];
+                inputs.extend(CircomWrapper::<Fr>::marshal_private_inputs(&input).unwrap());
+                inputs
+            })
+            .collect::<Vec<_>>();
+        let witnesses =
+            futures::future::join_all(jobs.iter().map(|inputs| pool.calculate(inputs.clone())))
+                .await;
+
+        // every job matches calculating its witness directly
+        for (inputs, witness) in jobs.iter().zip(witnesses) {
+            assert_eq!(witness.unwrap(), wrapper.extract_witness(inputs).unwrap());
+        }
+
+        // a pool over missing wasm fails up front
+        let missing = CircomWrapper::<Fr>::new(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("missing.wasm"),
+        );
+        assert!(matches!(
+            WitnessWorkerPool::new(missing, 2),
+            Err(GrapevineError::ArtifactError(_))
+        ));
+    }
+}
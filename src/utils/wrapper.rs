@@ -1,23 +1,153 @@
-use crate::utils::inputs::{random_f_bigint, serialize_phrase, serialize_username};
+use crate::errors::{GrapevineError, StepKind};
+use crate::utils::inputs::{
+    random_f_bigint, random_f_bigint_with, serialize_phrase_bytes, serialize_username,
+};
+use crate::utils::{MAX_SECRET_LENGTH, SECRET_FIELD_LENGTH};
+#[cfg(feature = "prover")]
 use ark_circom::{
     circom::{r1cs_reader, R1CS},
     WitnessCalculator,
 };
 use ark_ff::{BigInteger, PrimeField};
+use ark_std::rand::{rngs::StdRng, SeedableRng};
 use color_eyre::Result;
 use num_bigint::{BigInt, Sign};
+use sha2::{Digest, Sha256};
 use sonobe::Error as SonobeError;
-use std::{fs::File, io::BufReader, marker::PhantomData, path::PathBuf};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+#[cfg(feature = "prover")]
+use std::{
+    fs::File,
+    io::{BufReader, Cursor},
+};
+#[cfg(feature = "prover")]
+use wasmer::{Module, Store};
+
+// The secret phrase at the origin of a chain, as text or as arbitrary bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Phrase {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Phrase {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Phrase::Text(text) => text.as_bytes(),
+            Phrase::Bytes(bytes) => bytes,
+        }
+    }
+
+    // serializes the phrase into field-sized chunks
+    pub fn serialize(&self) -> Result<[BigInt; SECRET_FIELD_LENGTH], GrapevineError> {
+        serialize_phrase_bytes(self.as_bytes())
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct CircomPrivateInput {
-    pub phrase: Option<String>,
-    pub usernames: [Option<String>; 2],
-    pub auth_secrets: [Option<BigInt>; 2],
-    pub chaff: bool,
+    phrase: Option<Phrase>,
+    usernames: [Option<String>; 2],
+    auth_secrets: [Option<BigInt>; 2],
+    chaff: bool,
+    padding: bool,
+    // (seed, step) the chaff values are drawn from instead of the os rng
+    chaff_seed: Option<([u8; 32], u64)>,
 }
 
 impl CircomPrivateInput {
+    /**
+     * Creates private inputs for a step
+     * @dev chaff steps compute their own random vars so may not carry any fields
+     *
+     * @param phrase - the secret phrase (first degree only)
+     * @param usernames - the previous and current degree's usernames
+     * @param auth_secrets - the previous and current degree's auth secrets
+     * @param chaff - whether this is a chaff step
+     * @return - the inputs, or an error if chaff is set alongside any field or the phrase is
+     *           longer than 180 bytes
+     */
+    pub fn new(
+        phrase: Option<String>,
+        usernames: [Option<String>; 2],
+        auth_secrets: [Option<BigInt>; 2],
+        chaff: bool,
+    ) -> Result<Self, GrapevineError> {
+        let has_fields = phrase.is_some()
+            || usernames.iter().any(|u| u.is_some())
+            || auth_secrets.iter().any(|a| a.is_some());
+        if chaff && has_fields {
+            return Err(GrapevineError::InconsistentChaff);
+        }
+        if phrase.as_ref().map_or(false, |phrase| phrase.len() > MAX_SECRET_LENGTH) {
+            return Err(GrapevineError::InvalidInput(
+                "Phrase must be <= 180 bytes".to_string(),
+            ));
+        }
+        Ok(Self {
+            phrase: phrase.map(Phrase::Text),
+            usernames,
+            auth_secrets,
+            chaff,
+            padding: false,
+            chaff_seed: None,
+        })
+    }
+
+    /**
+     * Replaces the phrase with arbitrary bytes (e.g. a binary key) instead of a utf8 string
+     *
+     * @param bytes - the secret bytes (must be <= 180 bytes)
+     * @return - the inputs, or an error if they are chaff or the bytes are too long
+     */
+    pub fn with_phrase_bytes(mut self, bytes: Vec<u8>) -> Result<Self, GrapevineError> {
+        if self.chaff {
+            return Err(GrapevineError::InconsistentChaff);
+        }
+        if bytes.len() > MAX_SECRET_LENGTH {
+            return Err(GrapevineError::InvalidInput(
+                "Phrase must be <= 180 bytes".to_string(),
+            ));
+        }
+        self.phrase = Some(Phrase::Bytes(bytes));
+        Ok(self)
+    }
+
+    pub fn phrase(&self) -> Option<&Phrase> {
+        self.phrase.as_ref()
+    }
+
+    pub fn usernames(&self) -> &[Option<String>; 2] {
+        &self.usernames
+    }
+
+    pub fn auth_secrets(&self) -> &[Option<BigInt>; 2] {
+        &self.auth_secrets
+    }
+
+    pub fn is_chaff(&self) -> bool {
+        self.chaff
+    }
+
+    pub fn is_padding(&self) -> bool {
+        self.padding
+    }
+
+    pub fn chaff_seed(&self) -> Option<&([u8; 32], u64)> {
+        self.chaff_seed.as_ref()
+    }
+
+    pub fn kind(&self) -> StepKind {
+        match self.chaff {
+            true => StepKind::Chaff,
+            false => StepKind::Logic,
+        }
+    }
 
     /**
      * Creates empty inputs
@@ -30,7 +160,74 @@ impl CircomPrivateInput {
             usernames: [None, None],
             auth_secrets: [None, None],
             chaff,
+            padding: false,
+            chaff_seed: None,
+        }
+    }
+
+    /**
+     * Creates a chaff input that pads a chain past its last degree
+     * @dev marshals exactly like chaff but may be folded at logic (even) steps
+     */
+    pub fn padding() -> Self {
+        Self {
+            padding: true,
+            ..Self::empty(true)
+        }
+    }
+
+    /**
+     * Creates a chaff input whose random values are drawn from a seed rather than the os rng
+     * @dev the prover commits to the seed up front (see `chaff_seed_commitment`) and can later
+     *      reveal it so an auditor can reconstruct the exact chaff. Each step draws from its own
+     *      stream derived from (seed, step)
+     *
+     * @param seed - the committed chaff seed
+     * @param step - the index of the chaff step in the chain
+     */
+    pub fn deterministic_chaff(seed: [u8; 32], step: usize) -> Self {
+        Self {
+            chaff_seed: Some((seed, step as u64)),
+            ..Self::empty(true)
+        }
+    }
+
+    // draws this chaff (or padding) step's random values from (seed, step), see
+    // `deterministic_chaff`
+    pub fn with_chaff_seed(mut self, seed: [u8; 32], step: usize) -> Result<Self, GrapevineError> {
+        if !self.chaff {
+            return Err(GrapevineError::InvalidInput(
+                "Only chaff steps draw random values".to_string(),
+            ));
+        }
+        self.chaff_seed = Some((seed, step as u64));
+        Ok(self)
+    }
+
+    /**
+     * Renders the full circom input for a step in the input.json format snarkjs expects
+     * @dev chaff values are randomly sampled by marshalling, so the output differs between calls
+     *
+     * @param z_i - the ivc state the step is applied to
+     * @return - { ivc_input, phrase, usernames, auth_secrets } as arrays of decimal strings
+     */
+    pub fn to_snarkjs_input<F: PrimeField>(
+        &self,
+        z_i: &[F],
+    ) -> Result<serde_json::Value, GrapevineError> {
+        let to_strings = |values: &[BigInt]| -> Vec<String> {
+            values.iter().map(|value| value.to_string()).collect()
+        };
+        let ivc_input = z_i
+            .iter()
+            .map(|value| CircomWrapper::<F>::ark_primefield_to_num_bigint(*value))
+            .collect::<Vec<BigInt>>();
+        let mut input = serde_json::Map::new();
+        input.insert("ivc_input".to_string(), to_strings(&ivc_input).into());
+        for (label, values) in CircomWrapper::<F>::marshal_private_inputs(self)? {
+            input.insert(label, to_strings(&values).into());
         }
+        Ok(serde_json::Value::Object(input))
     }
 
     pub fn uninitialized(&self) -> bool {
@@ -41,11 +238,23 @@ impl CircomPrivateInput {
     }
 }
 
+// Location of a circom artifact: on disk or already loaded into memory
+#[derive(Clone, Debug)]
+pub enum Artifact {
+    Path(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+// Witnesses keyed by the digest of the marshalled circom inputs that produced them
+type WitnessCache = Arc<Mutex<HashMap<[u8; 32], Vec<BigInt>>>>;
+
 // Wrapper for circom functionalities (extract R1CS and witness)
 #[derive(Clone, Debug)]
 pub struct CircomWrapper<F: PrimeField> {
-    r1cs_path: PathBuf,
-    wc_path: PathBuf,
+    r1cs: Artifact,
+    wasm: Artifact,
+    max_witness_size: Option<usize>,
+    witness_cache: Option<WitnessCache>,
     _marker: PhantomData<F>,
 }
 
@@ -53,38 +262,271 @@ impl<F: PrimeField> CircomWrapper<F> {
     // creates a new instance of the wrapper with filepaths
     pub fn new(r1cs_path: PathBuf, wc_path: PathBuf) -> Self {
         Self {
-            r1cs_path,
-            wc_path,
+            r1cs: Artifact::Path(r1cs_path),
+            wasm: Artifact::Path(wc_path),
+            max_witness_size: None,
+            witness_cache: None,
             _marker: PhantomData,
         }
     }
 
+    // creates a new instance of the wrapper with in-memory artifacts (no filesystem access)
+    pub fn from_bytes(r1cs_bytes: Vec<u8>, wasm_bytes: Vec<u8>) -> Self {
+        Self {
+            r1cs: Artifact::Bytes(r1cs_bytes),
+            wasm: Artifact::Bytes(wasm_bytes),
+            max_witness_size: None,
+            witness_cache: None,
+            _marker: PhantomData,
+        }
+    }
+
+    // bounds the number of elements a calculated witness may contain (no limit by default)
+    pub fn with_max_witness_size(mut self, max: usize) -> Self {
+        self.max_witness_size = Some(max);
+        self
+    }
+
+    // caches calculated witnesses so repeated steps skip the wasm (shared between clones)
+    pub fn with_witness_cache(mut self) -> Self {
+        self.witness_cache = Some(WitnessCache::default());
+        self
+    }
+
+    // returns the number of witnesses in the cache
+    pub fn cached_witnesses(&self) -> usize {
+        self.witness_cache
+            .as_ref()
+            .map_or(0, |cache| cache.lock().unwrap().len())
+    }
+
+    // empties the witness cache (shared between clones) without disabling it
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.witness_cache {
+            cache.lock().unwrap().clear();
+        }
+    }
+
+    /**
+     * Persists the witness cache to disk so a restarted prover does not recompute witnesses
+     *
+     * @param path - the file to write the cache to
+     */
+    pub fn dump_cache(&self, path: &Path) -> Result<(), GrapevineError> {
+        let entries = match &self.witness_cache {
+            Some(cache) => cache
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(key, witness)| {
+                    let witness = witness.iter().map(|w| w.to_string()).collect();
+                    (*key, witness)
+                })
+                .collect::<Vec<([u8; 32], Vec<String>)>>(),
+            None => vec![],
+        };
+        let file = std::fs::File::create(path)
+            .map_err(|e| GrapevineError::SerializationError(e.to_string()))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), &entries)
+            .map_err(|e| GrapevineError::SerializationError(e.to_string()))
+    }
+
+    /**
+     * Loads witnesses persisted with `dump_cache` into the cache, enabling it if needed
+     *
+     * @param path - the file to read the cache from
+     */
+    pub fn load_cache(&mut self, path: &Path) -> Result<(), GrapevineError> {
+        let map_err = |e: String| GrapevineError::SerializationError(e);
+        let file = std::fs::File::open(path).map_err(|e| map_err(e.to_string()))?;
+        let entries: Vec<([u8; 32], Vec<String>)> =
+            serde_json::from_reader(std::io::BufReader::new(file))
+                .map_err(|e| map_err(e.to_string()))?;
+        let cache = self.witness_cache.get_or_insert_with(WitnessCache::default);
+        let mut cache = cache.lock().unwrap();
+        for (key, witness) in entries {
+            let witness = witness
+                .iter()
+                .map(|w| w.parse::<BigInt>().map_err(|e| map_err(e.to_string())))
+                .collect::<Result<Vec<BigInt>, GrapevineError>>()?;
+            cache.insert(key, witness);
+        }
+        Ok(())
+    }
+
+    // digests labelled circom inputs into a cache key
+    fn cache_key(inputs: &[(String, Vec<BigInt>)]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for (label, values) in inputs {
+            hasher.update((label.len() as u64).to_be_bytes());
+            hasher.update(label.as_bytes());
+            hasher.update((values.len() as u64).to_be_bytes());
+            for value in values {
+                let (_, bytes) = value.to_bytes_be();
+                hasher.update((bytes.len() as u64).to_be_bytes());
+                hasher.update(&bytes);
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    /**
+     * Calculates a witness, serving it from the cache if enabled
+     * @dev chaff inputs are randomly sampled and never reused so should not be cached
+     *
+     * @param inputs - the marshalled circom inputs
+     * @param cacheable - whether a newly calculated witness should be added to the cache
+     * @return - the witness
+     */
+    pub fn calculate_witness_cached(
+        &self,
+        inputs: &[(String, Vec<BigInt>)],
+        cacheable: bool,
+    ) -> Result<Vec<BigInt>, SonobeError> {
+        let cache = match &self.witness_cache {
+            Some(cache) => cache,
+            None => return self.calculate_witness(inputs),
+        };
+        let key = Self::cache_key(inputs);
+        if let Some(witness) = cache.lock().unwrap().get(&key) {
+            return Ok(witness.clone());
+        }
+        let witness = self.calculate_witness(inputs)?;
+        if cacheable {
+            cache.lock().unwrap().insert(key, witness.clone());
+        }
+        Ok(witness)
+    }
+
+    /**
+     * Checks a witness size against the configured maximum
+     *
+     * @param size - the number of elements in the witness
+     * @return - error if the witness exceeds the maximum
+     */
+    pub fn check_witness_size(&self, size: usize) -> Result<(), GrapevineError> {
+        match self.max_witness_size {
+            Some(max) if size > max => Err(GrapevineError::WitnessTooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    // parses the R1CS file from the artifact
+    #[cfg(feature = "prover")]
+    fn read_r1cs_file(&self) -> Result<r1cs_reader::R1CSFile<F>, SonobeError> {
+        Ok(match &self.r1cs {
+            Artifact::Path(path) => {
+                let reader = BufReader::new(File::open(path)?);
+                r1cs_reader::R1CSFile::<F>::new(reader)?
+            }
+            Artifact::Bytes(bytes) => r1cs_reader::R1CSFile::<F>::new(Cursor::new(bytes))?,
+        })
+    }
+
+    // reads the R1CS from the artifact
+    #[cfg(feature = "prover")]
+    pub fn read_r1cs(&self) -> Result<R1CS<F>, SonobeError> {
+        Ok(r1cs_reader::R1CS::<F>::from(self.read_r1cs_file()?))
+    }
+
+    // reads the number of public outputs declared in the R1CS header
+    #[cfg(feature = "prover")]
+    pub fn read_num_public_outputs(&self) -> Result<usize, SonobeError> {
+        Ok(self.read_r1cs_file()?.header.n_pub_out as usize)
+    }
+
+    /**
+     * Computes the length of every witness this circuit's wasm calculates
+     * @dev the R1CS wire count, including the constant wire at index 0
+     *
+     * @return - the number of elements a complete witness holds
+     */
+    #[cfg(feature = "prover")]
+    pub fn expected_witness_length(&self) -> Result<usize, GrapevineError> {
+        let r1cs_file = self
+            .read_r1cs_file()
+            .map_err(|e| GrapevineError::ArtifactError(e.to_string()))?;
+        Ok(r1cs_file.header.n_wires as usize)
+    }
+
+    // returns a short fingerprint of the circuit's R1CS (see `fingerprint_r1cs`)
+    #[cfg(feature = "prover")]
+    pub fn circuit_fingerprint(&self) -> Result<String, GrapevineError> {
+        let r1cs = self
+            .read_r1cs()
+            .map_err(|e| GrapevineError::ArtifactError(e.to_string()))?;
+        Ok(fingerprint_r1cs(&r1cs))
+    }
+
+    /**
+     * Estimates the peak bytes held while calculating a witness for this circuit
+     * @dev assumes every wire is held twice at peak: once as a num_bigint::BigInt returned by
+     *      the wasm calculator (stack header plus a heap allocation of the modulus' byte size)
+     *      and once as the converted field element. The wasm instance's own linear memory is
+     *      not included.
+     *
+     * @return - the estimated peak bytes
+     */
+    #[cfg(feature = "prover")]
+    pub fn estimate_witness_memory(&self) -> Result<usize, GrapevineError> {
+        let r1cs = self
+            .read_r1cs()
+            .map_err(|e| GrapevineError::ArtifactError(e.to_string()))?;
+        let field_bytes = (F::MODULUS_BIT_SIZE as usize + 7) / 8;
+        let per_wire = std::mem::size_of::<BigInt>() + field_bytes + std::mem::size_of::<F>();
+        Ok(r1cs.num_variables * per_wire)
+    }
+
     /**
      * Marshals the private inputs into the format expected by circom
      *
      * @param inputs - the private inputs
-     * @return - the marshalled inputs
+     * @return - the marshalled inputs, or an error if a secret does not fit in F
      */
-    pub fn marshal_private_inputs(inputs: &CircomPrivateInput) -> [(String, Vec<BigInt>); 3] {
+    pub fn marshal_private_inputs(
+        inputs: &CircomPrivateInput,
+    ) -> Result<Vec<(String, Vec<BigInt>)>, GrapevineError> {
+        // secrets outside of F would be silently reduced by the witness calculator
+        let modulus = BigInt::from_bytes_be(Sign::Plus, &F::MODULUS.to_bytes_be());
+        let in_field = |value: &BigInt| value.sign() != Sign::Minus && value < &modulus;
+        if !inputs.auth_secrets.iter().flatten().all(in_field) {
+            return Err(GrapevineError::FieldConversion(
+                "Auth secret is not below the field modulus".to_string(),
+            ));
+        }
+
+        // random values are drawn from the chaff seed if one is committed to
+        let mut seeded = inputs.chaff_seed.map(|(seed, step)| chaff_rng(&seed, step));
+        let mut sample = || match seeded.as_mut() {
+            Some(rng) => random_f_bigint_with::<F, _>(rng),
+            None => random_f_bigint::<F>(),
+        };
+
         // handle phrase presence (if not present infer chaff)
         let phrase = match &inputs.phrase {
-            Some(phrase) => serialize_phrase(&phrase).unwrap().to_vec(),
-            None => (0..6)
-                .map(|_| random_f_bigint::<F>())
-                .collect::<Vec<BigInt>>(),
+            Some(phrase) => phrase.serialize()?.to_vec(),
+            None => (0..6).map(|_| sample()).collect::<Vec<BigInt>>(),
         };
 
         // determine inputs: first step ([0] = None), Nth step ([1] = Some), and chaff ([2] = None)
         // marshal usernames
+        let serialize = |username: &String| {
+            serialize_username(username).map_err(|e| GrapevineError::InvalidInput(e.to_string()))
+        };
         let usernames = match inputs.usernames[0] {
             Some(_) => inputs
                 .usernames
                 .iter()
-                .map(|u| serialize_username(&u.clone().unwrap()).unwrap())
-                .collect::<Vec<BigInt>>(),
+                .map(|u| {
+                    let username = u.as_ref().ok_or(GrapevineError::InvalidInput(
+                        "Step has a previous username but no current one".to_string(),
+                    ))?;
+                    serialize(username)
+                })
+                .collect::<Result<Vec<BigInt>, GrapevineError>>()?,
             None => match &inputs.usernames[1] {
-                Some(username) => vec![BigInt::from(0), serialize_username(&username).unwrap()],
-                None => vec![random_f_bigint::<F>(), random_f_bigint::<F>()],
+                Some(username) => vec![BigInt::from(0), serialize(username)?],
+                None => vec![sample(), sample()],
             },
         };
 
@@ -93,41 +535,58 @@ impl<F: PrimeField> CircomWrapper<F> {
             Some(_) => inputs
                 .auth_secrets
                 .iter()
-                .map(|a| a.clone().unwrap())
-                .collect::<Vec<BigInt>>(),
+                .map(|a| {
+                    a.clone().ok_or(GrapevineError::InvalidInput(
+                        "Step has a previous auth secret but no current one".to_string(),
+                    ))
+                })
+                .collect::<Result<Vec<BigInt>, GrapevineError>>()?,
             None => match &inputs.auth_secrets[1] {
                 Some(auth_secret) => vec![BigInt::from(0), auth_secret.clone()],
-                None => vec![random_f_bigint::<F>(), random_f_bigint::<F>()],
+                None => vec![sample(), sample()],
             },
         };
 
         // label the inputs for circom
-        [
+        Ok(vec![
             ("phrase".to_string(), phrase),
             ("usernames".to_string(), usernames),
             ("auth_secrets".to_string(), auth_sec),
-        ]
+        ])
     }
 
     // aggregated function to obtain r1cs and witness from circom
+    #[cfg(feature = "prover")]
     pub fn extract_r1cs_and_witness(
         &self,
         inputs: &[(String, Vec<BigInt>)],
+        cacheable: bool,
     ) -> Result<(R1CS<F>, Option<Vec<F>>), SonobeError> {
         // extract R1CS
-        let file = File::open(&self.r1cs_path)?;
-        let reader = BufReader::new(file);
-        let r1cs_file = r1cs_reader::R1CSFile::<F>::new(reader)?;
-        let r1cs = r1cs_reader::R1CS::<F>::from(r1cs_file);
+        let r1cs = self.read_r1cs()?;
 
         // extract witness vector
-        let witness_vec = self.extract_witness(inputs)?;
+        let witness_vec = self.extract_witness_cached(inputs, cacheable)?;
 
         Ok((r1cs, Some(witness_vec)))
     }
 
     pub fn extract_witness(&self, inputs: &[(String, Vec<BigInt>)]) -> Result<Vec<F>, SonobeError> {
-        let witness_bigint = self.calculate_witness(inputs)?;
+        self.extract_witness_cached(inputs, false)
+    }
+
+    // extracts the witness as field elements, adding it to the cache if cacheable
+    pub fn extract_witness_cached(
+        &self,
+        inputs: &[(String, Vec<BigInt>)],
+        cacheable: bool,
+    ) -> Result<Vec<F>, SonobeError> {
+        let witness_bigint = self.calculate_witness_cached(inputs, cacheable)?;
+        Self::witness_to_field(&witness_bigint)
+    }
+
+    // converts a witness calculated by circom into field elements
+    pub fn witness_to_field(witness_bigint: &[BigInt]) -> Result<Vec<F>, SonobeError> {
         witness_bigint
             .iter()
             .map(|bigint| {
@@ -141,21 +600,65 @@ impl<F: PrimeField> CircomWrapper<F> {
             .collect()
     }
 
+    #[cfg(feature = "prover")]
     pub fn calculate_witness(
         &self,
         inputs: &[(String, Vec<BigInt>)],
     ) -> Result<Vec<BigInt>, SonobeError> {
-        let mut calculator = WitnessCalculator::new(&self.wc_path).map_err(|e| {
+        let mut calculator = self.witness_calculator()?;
+        self.calculate_witness_with(&mut calculator, inputs)
+    }
+
+    // instantiates a witness calculator from the wasm artifact
+    #[cfg(feature = "prover")]
+    pub fn witness_calculator(&self) -> Result<WitnessCalculator, SonobeError> {
+        let calculator = match &self.wasm {
+            Artifact::Path(path) => WitnessCalculator::new(path),
+            Artifact::Bytes(bytes) => Module::new(&Store::default(), bytes)
+                .map_err(|e| color_eyre::eyre::eyre!(e))
+                .and_then(WitnessCalculator::from_module),
+        };
+        calculator.map_err(|e| {
             SonobeError::WitnessCalculationError(format!(
                 "Failed to create WitnessCalculator: {}",
                 e
             ))
-        })?;
-        calculator
+        })
+    }
+
+    /**
+     * Calculates a witness with an already instantiated calculator
+     * @dev lets callers amortize the wasm instantiation across many witnesses
+     *
+     * @param calculator - a calculator created by `witness_calculator`
+     * @param inputs - the marshalled circom inputs
+     * @return - the witness
+     */
+    #[cfg(feature = "prover")]
+    pub fn calculate_witness_with(
+        &self,
+        calculator: &mut WitnessCalculator,
+        inputs: &[(String, Vec<BigInt>)],
+    ) -> Result<Vec<BigInt>, SonobeError> {
+        let witness = calculator
             .calculate_witness(inputs.iter().cloned(), true)
             .map_err(|e| {
                 SonobeError::WitnessCalculationError(format!("Failed to calculate witness: {}", e))
-            })
+            })?;
+        self.check_witness_size(witness.len())
+            .map_err(|e| SonobeError::WitnessCalculationError(e.to_string()))?;
+        Ok(witness)
+    }
+
+    // witness calculation requires the wasm runtime enabled by the `prover` feature
+    #[cfg(not(feature = "prover"))]
+    pub fn calculate_witness(
+        &self,
+        _inputs: &[(String, Vec<BigInt>)],
+    ) -> Result<Vec<BigInt>, SonobeError> {
+        Err(SonobeError::WitnessCalculationError(
+            "Witness calculation requires the `prover` feature".to_string(),
+        ))
     }
 
     pub fn num_bigint_to_ark_bigint(value: &BigInt) -> Result<F::BigInt, SonobeError> {
@@ -175,3 +678,303 @@ impl<F: PrimeField> CircomWrapper<F> {
         BigInt::from_bytes_be(Sign::Plus, &bytes)
     }
 }
+
+// Domain separator of the per-step chaff rng seeds
+const CHAFF_RNG_DOMAIN: &[u8] = b"grapevine-chaff-v1";
+
+// derives the rng a deterministic chaff step draws its values from
+fn chaff_rng(seed: &[u8; 32], step: u64) -> StdRng {
+    let mut hasher = Sha256::new();
+    hasher.update(CHAFF_RNG_DOMAIN);
+    hasher.update(seed);
+    hasher.update(step.to_be_bytes());
+    StdRng::from_seed(hasher.finalize().into())
+}
+
+/**
+ * Computes a short fingerprint identifying a circuit in logs
+ * @dev sha256 over the wire and constraint counts and up to 64 evenly spaced constraints,
+ *      truncated to 4 bytes. Not collision resistant, use `params_digest` to bind proofs
+ *
+ * @param r1cs - the circom R1CS to fingerprint
+ * @return - 8 hex characters
+ */
+#[cfg(feature = "prover")]
+pub fn fingerprint_r1cs<F: PrimeField>(r1cs: &R1CS<F>) -> String {
+    let mut hasher = Sha256::new();
+    for count in [r1cs.num_inputs, r1cs.num_aux, r1cs.num_variables, r1cs.constraints.len()] {
+        hasher.update((count as u64).to_be_bytes());
+    }
+    let stride = (r1cs.constraints.len() / 64).max(1);
+    for (a, b, c) in r1cs.constraints.iter().step_by(stride) {
+        for lc in [a, b, c] {
+            hasher.update((lc.len() as u64).to_be_bytes());
+            for (wire, coeff) in lc {
+                hasher.update((*wire as u64).to_be_bytes());
+                hasher.update(coeff.into_bigint().to_bytes_be());
+            }
+        }
+    }
+    hasher.finalize()[..4]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::MAX_USERNAME_LENGTH;
+    use ark_pallas::Fr;
+
+    #[test]
+    fn test_inconsistent_chaff() {
+        // chaff with a phrase is rejected
+        let res = CircomPrivateInput::new(
+            Some(String::from("This is a secret")),
+            [None, None],
+            [None, None],
+            true,
+        );
+        assert!(matches!(res, Err(GrapevineError::InconsistentChaff)));
+
+        // chaff with a username is rejected
+        let res =
+            CircomPrivateInput::new(None, [None, Some(String::from("alice"))], [None, None], true);
+        assert!(matches!(res, Err(GrapevineError::InconsistentChaff)));
+
+        // empty chaff and populated logic inputs are accepted
+        assert!(CircomPrivateInput::new(None, [None, None], [None, None], true).is_ok());
+        let input = CircomPrivateInput::new(
+            Some(String::from("This is a secret")),
+            [None, Some(String::from("alice"))],
+            [None, Some(BigInt::from(1))],
+            false,
+        )
+        .unwrap();
+        assert!(!input.is_chaff());
+    }
+
+    #[test]
+    fn test_marshal_malformed_inputs() {
+        // a previous username without a current one is rejected rather than panicking
+        let input = CircomPrivateInput::new(
+            None,
+            [Some(String::from("alice")), None],
+            [Some(BigInt::from(1)), Some(BigInt::from(2))],
+            false,
+        )
+        .unwrap();
+        assert!(matches!(
+            CircomWrapper::<Fr>::marshal_private_inputs(&input),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+
+        // as is a previous auth secret without a current one
+        let input = CircomPrivateInput::new(
+            None,
+            [Some(String::from("alice")), Some(String::from("bob"))],
+            [Some(BigInt::from(1)), None],
+            false,
+        )
+        .unwrap();
+        assert!(matches!(
+            CircomWrapper::<Fr>::marshal_private_inputs(&input),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+
+        // and a username too long to pack into a field element
+        let input = CircomPrivateInput::new(
+            None,
+            [None, Some("a".repeat(MAX_USERNAME_LENGTH + 1))],
+            [None, Some(BigInt::from(1))],
+            false,
+        )
+        .unwrap();
+        assert!(matches!(
+            CircomWrapper::<Fr>::marshal_private_inputs(&input),
+            Err(GrapevineError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_estimate_witness_memory() {
+        let wrapper = CircomWrapper::<Fr>::new(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        );
+        let estimate = wrapper.estimate_witness_memory().unwrap();
+
+        // measure the bytes held by a real witness in both representations
+        let mut inputs = vec![("ivc_input".to_string(), vec![BigInt::from(0); 4])];
+        inputs.extend(
+            CircomWrapper::<Fr>::marshal_private_inputs(&CircomPrivateInput::empty(true)).unwrap(),
+        );
+        let witness_bigint = wrapper.calculate_witness(&inputs).unwrap();
+        let witness = wrapper.extract_witness(&inputs).unwrap();
+        let measured = witness_bigint
+            .iter()
+            .map(|w| std::mem::size_of::<BigInt>() + w.to_bytes_be().1.len())
+            .sum::<usize>()
+            + witness.len() * std::mem::size_of::<Fr>();
+
+        assert!(estimate * 10 >= measured && estimate <= measured * 10);
+    }
+
+    #[test]
+    fn test_witness_cache() {
+        let r1cs_path = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+        let wasm_path = PathBuf::from("./circom/artifacts/grapevine.wasm");
+        let wrapper = CircomWrapper::<Fr>::new(r1cs_path.clone(), wasm_path).with_witness_cache();
+
+        // a logic step is cached, a chaff step is not
+        let logic = CircomPrivateInput::new(
+            Some(String::from("This is a secret")),
+            [None, Some(String::from("alice"))],
+            [None, Some(BigInt::from(1))],
+            false,
+        )
+        .unwrap();
+        let mut logic_inputs = vec![("ivc_input".to_string(), vec![BigInt::from(0); 4])];
+        logic_inputs.extend(CircomWrapper::<Fr>::marshal_private_inputs(&logic).unwrap());
+        let mut chaff_inputs = vec![("ivc_input".to_string(), vec![BigInt::from(0); 4])];
+        chaff_inputs.extend(
+            CircomWrapper::<Fr>::marshal_private_inputs(&CircomPrivateInput::empty(true)).unwrap(),
+        );
+        let witness = wrapper.extract_witness_cached(&logic_inputs, true).unwrap();
+        wrapper.extract_witness_cached(&chaff_inputs, false).unwrap();
+        assert_eq!(wrapper.cached_witnesses(), 1);
+
+        // dump and reload into a wrapper whose wasm cannot calculate witnesses
+        let path = std::env::temp_dir().join("grapevine_witness_cache.json");
+        wrapper.dump_cache(&path).unwrap();
+        let mut reloaded = CircomWrapper::<Fr>::new(r1cs_path, PathBuf::from("missing.wasm"));
+        reloaded.load_cache(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(reloaded.cached_witnesses(), 1);
+
+        // the logic step is served from the reloaded cache
+        assert_eq!(reloaded.extract_witness(&logic_inputs).unwrap(), witness);
+        assert!(reloaded.extract_witness(&chaff_inputs).is_err());
+    }
+
+    #[test]
+    fn test_circuit_fingerprint() {
+        let r1cs_path = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+        let wasm_path = PathBuf::from("./circom/artifacts/grapevine.wasm");
+        let wrapper = CircomWrapper::<Fr>::new(r1cs_path.clone(), wasm_path.clone());
+        let fingerprint = wrapper.circuit_fingerprint().unwrap();
+        assert_eq!(fingerprint.len(), 8);
+        assert!(fingerprint.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // the same circuit has a stable fingerprint however it is loaded
+        let bytes_wrapper = CircomWrapper::<Fr>::from_bytes(
+            std::fs::read(&r1cs_path).unwrap(),
+            std::fs::read(&wasm_path).unwrap(),
+        );
+        assert_eq!(bytes_wrapper.circuit_fingerprint().unwrap(), fingerprint);
+
+        // a circuit with a different structure does not
+        let mut r1cs = wrapper.read_r1cs().unwrap();
+        r1cs.num_aux += 1;
+        assert_ne!(fingerprint_r1cs(&r1cs), fingerprint);
+    }
+
+    #[test]
+    fn test_max_witness_size() {
+        let r1cs_path = PathBuf::from("./circom/artifacts/grapevine.r1cs");
+        let wasm_path = PathBuf::from("./circom/artifacts/grapevine.wasm");
+
+        // no limit by default
+        let wrapper = CircomWrapper::<Fr>::new(r1cs_path, wasm_path);
+        assert!(wrapper.check_witness_size(usize::MAX).is_ok());
+
+        // stub a witness size exceeding a small configured max
+        let wrapper = wrapper.with_max_witness_size(8);
+        assert!(wrapper.check_witness_size(8).is_ok());
+        assert!(matches!(
+            wrapper.check_witness_size(9),
+            Err(GrapevineError::WitnessTooLarge { size: 9, max: 8 })
+        ));
+    }
+
+    #[test]
+    fn test_to_snarkjs_input() {
+        let input = CircomPrivateInput::new(
+            Some(String::from("This is a secret")),
+            [None, Some(String::from("alice"))],
+            [None, Some(BigInt::from(7))],
+            false,
+        )
+        .unwrap();
+        let z_i = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(0)];
+        let json = input.to_snarkjs_input(&z_i).unwrap();
+
+        // every signal is an array of decimal strings
+        let lengths = [("ivc_input", 4), ("phrase", 6), ("usernames", 2), ("auth_secrets", 2)];
+        for (label, len) in lengths {
+            let values = json[label].as_array().unwrap();
+            assert_eq!(values.len(), len);
+            assert!(values
+                .iter()
+                .all(|v| v.as_str().unwrap().chars().all(|c| c.is_ascii_digit())));
+        }
+        assert_eq!(json["ivc_input"][2], serde_json::json!("3"));
+        assert_eq!(json["usernames"][0], serde_json::json!("0"));
+        assert_eq!(json["auth_secrets"][1], serde_json::json!("7"));
+    }
+
+    #[test]
+    fn test_out_of_field_auth_secret() {
+        let modulus = BigInt::from_bytes_be(Sign::Plus, &Fr::MODULUS.to_bytes_be());
+        let secret_input = |secret: BigInt| {
+            CircomPrivateInput::new(
+                Some(String::from("This is a secret")),
+                [None, Some(String::from("alice"))],
+                [None, Some(secret)],
+                false,
+            )
+            .unwrap()
+        };
+
+        // the largest element of F is accepted, the modulus itself is not
+        let max = secret_input(&modulus - 1);
+        assert!(CircomWrapper::<Fr>::marshal_private_inputs(&max).is_ok());
+        for secret in [modulus.clone(), BigInt::from(-1)] {
+            assert!(matches!(
+                CircomWrapper::<Fr>::marshal_private_inputs(&secret_input(secret)),
+                Err(GrapevineError::FieldConversion(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_chaff_uniform() {
+        // 2000 chaff steps marshal 20000 values, 1250 per bucket
+        crate::test_utils::assert_chaff_uniform(2000).unwrap();
+    }
+
+    #[test]
+    fn test_expected_witness_length() {
+        let wrapper = CircomWrapper::<Fr>::new(
+            PathBuf::from("./circom/artifacts/grapevine.r1cs"),
+            PathBuf::from("./circom/artifacts/grapevine.wasm"),
+        );
+        let expected = wrapper.expected_witness_length().unwrap();
+
+        // logic and chaff witnesses alike have exactly the wire count
+        let logic = CircomPrivateInput::new(
+            Some(String::from("This is a secret")),
+            [None, Some(String::from("alice"))],
+            [None, Some(BigInt::from(1))],
+            false,
+        )
+        .unwrap();
+        for input in [logic, CircomPrivateInput::empty(true)] {
+            let mut inputs = vec![("ivc_input".to_string(), vec![BigInt::from(0); 4])];
+            inputs.extend(CircomWrapper::<Fr>::marshal_private_inputs(&input).unwrap());
+            assert_eq!(wrapper.extract_witness(&inputs).unwrap().len(), expected);
+        }
+        assert_eq!(expected, wrapper.read_r1cs().unwrap().num_variables);
+    }
+}
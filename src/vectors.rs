@@ -0,0 +1,140 @@
+use ark_ff::UniformRand;
+use ark_pallas::Fr;
+use ark_std::rand::{distributions::Alphanumeric, rngs::StdRng, Rng, SeedableRng};
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circom::GrapevineFCircuit,
+    errors::GrapevineError,
+    nova::replay_native_with_trace,
+    utils::{
+        inputs::{get_z0, reconstruct_inputs},
+        wrapper::CircomWrapper,
+    },
+};
+
+// One degree of a test vector chain (auth secret as a decimal string)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectorDegree {
+    pub username: String,
+    pub auth_secret: String,
+}
+
+// Conformance vectors for a grapevine chain: the inputs and every state they produce
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub seed: u64,
+    pub phrase: String,
+    pub path: Vec<TestVectorDegree>,
+    // the state after each (logic, chaff) step, as decimal strings
+    pub intermediate_states: Vec<Vec<String>>,
+    pub final_state: Vec<String>,
+}
+
+impl TestVectors {
+    // serializes the vectors into a JSON document
+    pub fn to_json(&self) -> Result<String, GrapevineError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| GrapevineError::SerializationError(e.to_string()))
+    }
+
+    // parses vectors from a JSON document
+    pub fn from_json(json: &str) -> Result<Self, GrapevineError> {
+        serde_json::from_str(json).map_err(|e| GrapevineError::SerializationError(e.to_string()))
+    }
+
+    // returns the (username, auth secret) path of the chain
+    pub fn path(&self) -> Result<Vec<(String, BigInt)>, GrapevineError> {
+        self.path
+            .iter()
+            .map(|degree| {
+                let auth_secret = degree.auth_secret.parse::<BigInt>().map_err(|e| {
+                    GrapevineError::SerializationError(e.to_string())
+                })?;
+                Ok((degree.username.clone(), auth_secret))
+            })
+            .collect()
+    }
+}
+
+/**
+ * Generates deterministic test vectors for cross-implementation conformance testing
+ * @dev the phrase, usernames and auth secrets are all drawn from an rng seeded with `seed`
+ *
+ * @param f_circuit - the grapevine function circuit used to compute the expected states
+ * @param seed - the seed the chain is derived from
+ * @param degrees - the degree of separation of the chain
+ * @return - the chain inputs with its intermediate and final states
+ */
+pub fn generate_test_vectors(
+    f_circuit: &GrapevineFCircuit<Fr>,
+    seed: u64,
+    degrees: usize,
+) -> Result<TestVectors, GrapevineError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut random_string = |len: usize| -> String {
+        (&mut rng).sample_iter(&Alphanumeric).take(len).map(char::from).collect()
+    };
+    let phrase = random_string(32);
+    let usernames = (0..degrees).map(|_| random_string(16)).collect::<Vec<String>>();
+    let path = usernames
+        .into_iter()
+        .map(|username| {
+            let secret = CircomWrapper::<Fr>::ark_primefield_to_num_bigint(Fr::rand(&mut rng));
+            (username, secret)
+        })
+        .collect::<Vec<(String, BigInt)>>();
+
+    // fold the chain natively, recording the state after every step
+    let inputs = reconstruct_inputs(&phrase, &path)?;
+    let trace = replay_native_with_trace(f_circuit, get_z0::<Fr>().to_vec(), &inputs)?;
+    let z_i = trace.last().cloned().unwrap_or_else(|| get_z0::<Fr>().to_vec());
+    let intermediate_states = trace.iter().map(|z| state_to_strings(z)).collect();
+
+    Ok(TestVectors {
+        seed,
+        phrase,
+        path: path
+            .into_iter()
+            .map(|(username, auth_secret)| TestVectorDegree {
+                username,
+                auth_secret: auth_secret.to_string(),
+            })
+            .collect(),
+        intermediate_states,
+        final_state: state_to_strings(&z_i),
+    })
+}
+
+// renders a state as decimal strings
+fn state_to_strings(z: &[Fr]) -> Vec<String> {
+    z.iter()
+        .map(|value| CircomWrapper::<Fr>::ark_primefield_to_num_bigint(*value).to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova::replay_native;
+    use crate::test_utils::sample_circuit;
+
+    #[test]
+    fn test_generate_test_vectors() {
+        let f_circuit = sample_circuit();
+
+        // the same seed always yields the same document
+        let vectors = generate_test_vectors(&f_circuit, 42, 3).unwrap();
+        let json = vectors.to_json().unwrap();
+        assert_eq!(generate_test_vectors(&f_circuit, 42, 3).unwrap().to_json().unwrap(), json);
+        assert_ne!(generate_test_vectors(&f_circuit, 43, 3).unwrap(), vectors);
+        assert_eq!(vectors.intermediate_states.len(), 6);
+
+        // replaying the stated inputs reproduces the stated final state
+        let parsed = TestVectors::from_json(&json).unwrap();
+        let inputs = reconstruct_inputs(&parsed.phrase, &parsed.path().unwrap()).unwrap();
+        let z_i = replay_native(&f_circuit, get_z0().to_vec(), &inputs).unwrap();
+        assert_eq!(state_to_strings(&z_i), parsed.final_state);
+    }
+}